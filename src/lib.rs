@@ -1,10 +1,15 @@
 #![cfg_attr(not(test), no_std)]
 
-use core::{num, ops::{Index, IndexMut}};
+use core::{cell::RefCell, marker::PhantomData, num, ops::Index};
 use core::result::Result;
 
 use gc_headers::{GarbageCollectingHeap, HeapError, Pointer, Tracer};
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
 fn independent_elements_from<T>(i: usize, j: usize, slice: &mut [T]) -> Option<(&mut T, &mut T)> {
     if i == j || i >= slice.len() || j >= slice.len() {
         None
@@ -24,49 +29,219 @@ struct BlockInfo {
     num_times_copied: usize,
 }
 
+/// One entry of a block table, with `C` holding whatever the heap's collector keeps for
+/// each block. Its fields are private; callers only name this type to provide storage for
+/// the block table of a heap built on their own memory, e.g. `[BlockSlot::default(); 64]`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BlockSlot<C = ()> {
+    info: Option<BlockInfo>,
+    // How many times this block number has been freed.
+    epoch: usize,
+    // The in-use bits of blocks `64 * i..64 * (i + 1)`, where `i` is this slot's index.
+    in_use: u64,
+    collector: C,
+}
+
+// Per-block collector state that `trace_interior` can thread its worklist through.
+trait ScanLink {
+    fn next_scan(&mut self) -> &mut usize;
+}
+
+const BLOCKS_PER_WORD: usize = u64::BITS as usize;
+
+// Which blocks are in use is also kept as a bitmap, with one word of bits for every 64 blocks
+// stored in the first slots, so that finding the lowest unused block or the next block in use
+// skips 64 blocks at a time.
 #[derive(Copy, Clone, Debug)]
-struct BlockTable<const MAX_BLOCKS: usize> {
-    block_info: [Option<BlockInfo>; MAX_BLOCKS],
+struct BlockTable<B, C = ()> {
+    slots: B,
+    num_in_use: usize,
+    collector: PhantomData<C>,
 }
 
-impl<const MAX_BLOCKS: usize> Index<usize> for BlockTable<MAX_BLOCKS> {
+type FixedBlockTable<const MAX_BLOCKS: usize, C = ()> = BlockTable<[BlockSlot<C>; MAX_BLOCKS], C>;
+
+impl<C, B: AsRef<[BlockSlot<C>]>> Index<usize> for BlockTable<B, C> {
     type Output = Option<BlockInfo>;
 
     fn index(&self, index: usize) -> &Self::Output {
-        &self.block_info[index]
+        &self.slots.as_ref()[index].info
     }
 }
 
-impl<const MAX_BLOCKS: usize> IndexMut<usize> for BlockTable<MAX_BLOCKS> {
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.block_info[index]
+impl<const MAX_BLOCKS: usize, C: Copy + Default> FixedBlockTable<MAX_BLOCKS, C> {
+    fn new() -> Self {
+        Self::in_slots([BlockSlot::default(); MAX_BLOCKS])
+    }
+
+    /// Slides every live block in `heap` down to address 0 and discards the space above them.
+    fn compact<W: AsRef<[u64]> + AsMut<[u64]>>(&mut self, heap: &mut RamHeap<W>) {
+        let mut live = [0; MAX_BLOCKS];
+        let mut num_live = 0;
+        for block in self.blocks_in_use() {
+            live[num_live] = block;
+            num_live += 1;
+        }
+
+        // Sliding in address order guarantees that a block's destination never
+        // overlaps a live block that has yet to be moved.
+        let live = &mut live[..num_live];
+        live.sort_unstable_by_key(|b| self[*b].unwrap().start);
+
+        let mut next_start = 0;
+        for &b in live.iter() {
+            let info = self[b].unwrap();
+            if info.start != next_start {
+                self.set(b, Some(heap.slide(&info, next_start)));
+            }
+            next_start += info.size;
+        }
+        heap.truncate(next_start);
     }
 }
 
-impl<const MAX_BLOCKS: usize> BlockTable<MAX_BLOCKS> {
-    fn new() -> Self {
-        Self {
-            block_info: [None; MAX_BLOCKS],
+impl<C: Copy + Default, B: Buffer<BlockSlot<C>>> BlockTable<B, C> {
+    fn in_slots(mut slots: B) -> Self {
+        slots.as_mut().fill(BlockSlot::default());
+        Self { slots, num_in_use: 0, collector: PhantomData }
+    }
+
+    fn len(&self) -> usize {
+        self.slots.as_ref().len()
+    }
+
+    fn slot(&self, block: usize) -> &BlockSlot<C> {
+        &self.slots.as_ref()[block]
+    }
+
+    fn slot_mut(&mut self, block: usize) -> &mut BlockSlot<C> {
+        &mut self.slots.as_mut()[block]
+    }
+
+    fn collector(&self, block: usize) -> &C {
+        &self.slot(block).collector
+    }
+
+    fn collector_mut(&mut self, block: usize) -> &mut C {
+        &mut self.slot_mut(block).collector
+    }
+
+    /// Adds unused blocks until there are `len`, returning false if the slots cannot grow.
+    /// The new blocks' bits are in new slots or in words that were past the old last block,
+    /// so they are already clear.
+    fn grow(&mut self, len: usize) -> bool {
+        self.slots.grow_to(len, BlockSlot::default())
+    }
+
+    fn in_use(&self, block: usize) -> bool {
+        self.slot(block / BLOCKS_PER_WORD).in_use & (1 << (block % BLOCKS_PER_WORD)) != 0
+    }
+
+    /// The lowest block at or above `from` that is in use, if `in_use`, or unused otherwise.
+    fn next_block(&self, from: usize, in_use: bool) -> Option<usize> {
+        let words = self.len().div_ceil(BLOCKS_PER_WORD);
+        let flip = if in_use { 0 } else { u64::MAX };
+        let mut word = from / BLOCKS_PER_WORD;
+        if word >= words {
+            return None;
+        }
+        let mut bits = (self.slot(word).in_use ^ flip) & (u64::MAX << (from % BLOCKS_PER_WORD));
+        while bits == 0 {
+            word += 1;
+            if word >= words {
+                return None;
+            }
+            bits = self.slot(word).in_use ^ flip;
         }
+        // Bits past the last block are clear, so they only ever match an unused block.
+        Some(word * BLOCKS_PER_WORD + bits.trailing_zeros() as usize).filter(|b| *b < self.len())
     }
 
-    fn available_block(&self) -> Option<usize> {
-        //todo!("Return the lowest numbered unused block");
-        for (i, block) in self.block_info.iter().enumerate(){
-            if block.is_none(){
-                return Some(i);
+    fn set(&mut self, block: usize, info: Option<BlockInfo>) {
+        let bit = 1 << (block % BLOCKS_PER_WORD);
+        match (self[block].is_some(), info.is_some()) {
+            (false, true) => {
+                self.slot_mut(block / BLOCKS_PER_WORD).in_use |= bit;
+                self.num_in_use += 1;
             }
+            (true, false) => {
+                self.slot_mut(block / BLOCKS_PER_WORD).in_use &= !bit;
+                self.num_in_use -= 1;
+                self.slot_mut(block).epoch += 1;
+            }
+            _ => {}
         }
-        None
+        self.slot_mut(block).info = info;
+    }
+
+    /// Marks every block reachable from those already marked. Only blocks for which
+    /// `traced` holds are scanned; the rest are marked when reached but not looked inside.
+    fn trace_interior<S: Fn(usize, &C) -> bool, F: Fn(usize, &C, usize) -> Result<u64, HeapError>>(
+        &mut self,
+        blocks: &mut [bool],
+        traced: S,
+        load: F,
+    ) -> Result<(), HeapError>
+    where
+        C: ScanLink,
+    {
+        // The blocks waiting to be scanned form a stack threaded through their `next_scan`.
+        let mut to_scan = self.len();
+        for block in 0..self.len() {
+            if blocks[block] && self[block].is_some() && traced(block, self.collector(block)) {
+                *self.collector_mut(block).next_scan() = to_scan;
+                to_scan = block;
+            }
+        }
+
+        while to_scan < self.len() {
+            let block = to_scan;
+            to_scan = *self.collector_mut(block).next_scan();
+            let info = self[block].unwrap();
+            for address in info.start..info.start + info.size {
+                if let Some(p) = decode_pointer(load(block, self.collector(block), address)?) {
+                    if self.address(p).is_ok() && !blocks[p.block_num()] {
+                        blocks[p.block_num()] = true;
+                        if traced(p.block_num(), self.collector(p.block_num())) {
+                            *self.collector_mut(p.block_num()).next_scan() = to_scan;
+                            to_scan = p.block_num();
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn epoch(&self, block: usize) -> Option<usize> {
+        self.slots.as_ref().get(block).map(|slot| slot.epoch)
+    }
+
+    fn available_block(&self) -> Option<usize> {
+        self.next_block(0, false)
+    }
+
+    fn num_allocated_blocks(&self) -> usize {
+        self.num_in_use
     }
 
     fn blocks_in_use(&self) -> impl Iterator<Item = usize> + '_ {
-        (0..MAX_BLOCKS).filter(|b| self.block_info[*b].is_some())
+        let mut from = 0;
+        core::iter::from_fn(move || {
+            let block = self.next_block(from, true)?;
+            from = block + 1;
+            Some(block)
+        })
+    }
+
+    fn usage(&self) -> (usize, usize) {
+        let words = self.blocks_in_use().map(|b| self[b].unwrap().size).sum();
+        (self.num_in_use, words)
     }
 
     fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
         self.blocks_in_use()
-            .map(|b| (b, self.block_info[b].unwrap().num_times_copied))
+            .map(|b| (b, self[b].unwrap().num_times_copied))
     }
 
     fn address(&self, p: Pointer) -> Result<usize, HeapError> {
@@ -74,11 +249,11 @@ impl<const MAX_BLOCKS: usize> BlockTable<MAX_BLOCKS> {
         
 
 
-        if p.block_num() >= MAX_BLOCKS{
-            return Err(HeapError::IllegalBlock(p.block_num(), MAX_BLOCKS - 1));
+        if p.block_num() >= self.len(){
+            return Err(HeapError::IllegalBlock(p.block_num(), self.len() - 1));
         }
 
-        let block = match self.block_info[p.block_num()]
+        let block = match self[p.block_num()]
         {
             None => return Err(HeapError::UnallocatedBlock(p.block_num())),
             Some(b) => b
@@ -103,29 +278,541 @@ impl<const MAX_BLOCKS: usize> BlockTable<MAX_BLOCKS> {
     }
 
     fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
-        match self.block_info.get(block) {
+        match self.slots.as_ref().get(block) {
             None => None,
-            Some(info) => info.map(|info| Pointer::new(block, info.size)),
+            Some(slot) => slot.info.map(|info| Pointer::new(block, info.size)),
+        }
+    }
+
+    /// Checks the table's own bookkeeping, then checks each live block against
+    /// `placement`, which names the heap holding each block and that heap's `next_address`.
+    fn verify<F: Fn(usize, &BlockInfo) -> (usize, usize)>(&self, max_copies: usize, placement: F) -> Violations {
+        let mut violations = Violations::default();
+        let mut num_in_use = 0;
+        for (block, slot) in self.slots.as_ref().iter().enumerate() {
+            if self.in_use(block) != slot.info.is_some() {
+                violations.push(Violation::Bitmap(block));
+            }
+            if slot.info.is_some() {
+                num_in_use += 1;
+            }
+        }
+        if num_in_use != self.num_in_use {
+            violations.push(Violation::BlockCount(self.num_in_use, num_in_use));
+        }
+
+        let live = || self.slots.as_ref().iter().enumerate().filter_map(|(b, slot)| slot.info.map(|i| (b, i)));
+        for (block, info) in live() {
+            let (heap, next_address) = placement(block, &info);
+            if info.start + info.size > next_address {
+                violations.push(Violation::OutOfBounds(block, info.start + info.size, next_address));
+            }
+            if info.num_times_copied > max_copies {
+                violations.push(Violation::CopyCount(block, info.num_times_copied, max_copies));
+            }
+            for (other, other_info) in live().skip_while(|(other, _)| *other <= block) {
+                if placement(other, &other_info).0 == heap
+                    && info.start < other_info.start + other_info.size
+                    && other_info.start < info.start + info.size
+                {
+                    violations.push(Violation::Overlap(block, other));
+                }
+            }
+        }
+        violations
+    }
+
+    /// Reports every live block that shares a word with the free range at `start`.
+    fn verify_free(&self, start: usize, size: usize, violations: &mut Violations) {
+        for (block, slot) in self.slots.as_ref().iter().enumerate() {
+            if let Some(info) = slot.info {
+                if info.start < start + size && start < info.start + info.size {
+                    violations.push(Violation::OverlapsFree(block, start));
+                }
+            }
+        }
+    }
+}
+
+const POINTER_TAG: u64 = 1 << 63;
+
+/// Encodes `p` as a word that `CopyingHeap` and `GenerationalHeap` recognize as a
+/// reference when they scan the contents of live blocks during a collection.
+pub fn encode_pointer(p: Pointer) -> u64 {
+    POINTER_TAG | ((p.block_num() as u64) << 32) | p.len() as u64
+}
+
+/// Recovers the `Pointer` stored by `encode_pointer`, or `None` for an ordinary value.
+pub fn decode_pointer(word: u64) -> Option<Pointer> {
+    if word & POINTER_TAG == 0 {
+        None
+    } else {
+        Some(Pointer::new(
+            ((word & !POINTER_TAG) >> 32) as usize,
+            (word & 0xFFFF_FFFF) as usize,
+        ))
+    }
+}
+
+/// A `Pointer` stamped with the epoch of its block at the time it was minted.
+/// Every time a block number is freed its epoch advances, so a stamped pointer
+/// to a recycled block is rejected even if the new block has the same size.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EpochPointer {
+    pointer: Pointer,
+    epoch: usize,
+}
+
+impl EpochPointer {
+    pub fn pointer(&self) -> Pointer {
+        self.pointer
+    }
+
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EpochError {
+    Heap(HeapError),
+    /// Block number, epoch of the pointer, current epoch of the block.
+    StalePointer(usize, usize, usize),
+}
+
+impl From<HeapError> for EpochError {
+    fn from(e: HeapError) -> Self {
+        EpochError::Heap(e)
+    }
+}
+
+/// A heap that can tell a pointer to a block from one to an earlier block with the
+/// same number. `epoch_pointer` stamps a pointer with its block's current epoch, and
+/// the `checked_*` methods reject a stamped pointer once that block has been freed.
+///
+/// Only `EpochPointer`s are checked. A plain `Pointer` carries no epoch, so `address`,
+/// `load` and `store` still accept a stale pointer whenever the block number has been
+/// reused for a block of the same size.
+pub trait EpochCheckedHeap: GarbageCollectingHeap {
+    fn block_epoch(&self, block: usize) -> Option<usize>;
+
+    fn epoch_pointer(&self, p: Pointer) -> Result<EpochPointer, EpochError> {
+        self.address(p)?;
+        Ok(EpochPointer {
+            pointer: p,
+            epoch: self.block_epoch(p.block_num()).unwrap(),
+        })
+    }
+
+    fn checked_address(&self, p: EpochPointer) -> Result<usize, EpochError> {
+        let block = p.pointer.block_num();
+        match self.block_epoch(block) {
+            Some(epoch) if epoch != p.epoch => Err(EpochError::StalePointer(block, p.epoch, epoch)),
+            _ => Ok(self.address(p.pointer)?),
         }
     }
+
+    fn checked_load(&self, p: EpochPointer) -> Result<u64, EpochError> {
+        self.checked_address(p)?;
+        Ok(self.load(p.pointer)?)
+    }
+
+    fn checked_store(&mut self, p: EpochPointer, value: u64) -> Result<(), EpochError> {
+        self.checked_address(p)?;
+        Ok(self.store(p.pointer, value)?)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    Heap(HeapError),
+    /// Address of a word that holds `POISON`.
+    Poisoned(usize),
+}
+
+impl From<HeapError> for LoadError {
+    fn from(e: HeapError) -> Self {
+        LoadError::Heap(e)
+    }
+}
+
+/// A heap whose loads can report reading a poisoned word. Plain `load` returns `POISON`
+/// like any other value, while `poison_checked_load` reports `LoadError::Poisoned`.
+/// Freshly allocated words read as zero, so only a pointer into memory that is not part
+/// of any block can reach a poisoned word. Without the `poison` feature nothing is
+/// poisoned and the two loads agree.
+pub trait PoisonCheckedHeap {
+    fn poison_checked_load(&self, p: Pointer) -> Result<u64, LoadError>;
+}
+
+/// What an explicit collection reclaimed, and what it left behind.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CollectionSummary {
+    pub blocks_reclaimed: usize,
+    pub words_reclaimed: usize,
+    pub blocks_live: usize,
+    pub words_live: usize,
+}
+
+/// A broken invariant reported by `VerifiableHeap::verify`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// The block table's bitmap disagrees with its entry for this block about whether it is in use.
+    Bitmap(usize),
+    /// Blocks recorded as in use, blocks actually in use.
+    BlockCount(usize, usize),
+    /// Two live blocks in the same heap that share at least one word.
+    Overlap(usize, usize),
+    /// Block number, end of the block, `next_address` of the heap it should be in.
+    OutOfBounds(usize, usize, usize),
+    /// Block number, start of the free space it overlaps.
+    OverlapsFree(usize, usize),
+    /// Block number, times it has been copied, most copies its heap could have made.
+    CopyCount(usize, usize, usize),
+    /// Block number, times it has been copied, the generation it is in but that count rules out.
+    Generation(usize, usize, usize),
+    /// An inactive semispace has this `next_address` instead of being empty.
+    Strays(usize),
 }
 
+const MAX_VIOLATIONS: usize = 16;
+
+/// The first `MAX_VIOLATIONS` violations found by `verify`, along with the total number found.
 #[derive(Copy, Clone, Debug)]
-struct RamHeap<const HEAP_SIZE: usize> {
-    heap: [u64; HEAP_SIZE],
+pub struct Violations {
+    found: [Option<Violation>; MAX_VIOLATIONS],
+    count: usize,
+}
+
+impl Default for Violations {
+    fn default() -> Self {
+        Self {
+            found: [None; MAX_VIOLATIONS],
+            count: 0,
+        }
+    }
+}
+
+impl Violations {
+    fn push(&mut self, violation: Violation) {
+        if self.count < MAX_VIOLATIONS {
+            self.found[self.count] = Some(violation);
+        }
+        self.count += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The total number found, which may exceed the number retained.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Violation> + '_ {
+        self.found.iter().filter_map(|v| *v)
+    }
+}
+
+/// A heap that can check its internal invariants after any operation, unlike
+/// `assert_no_strays`, which only checks the space a collection vacated.
+pub trait VerifiableHeap {
+    fn verify(&self) -> Violations;
+}
+
+/// A heap that the application can ask to collect outside of `malloc`. The summary compares
+/// the blocks and words in use across the whole heap before and after the collection.
+pub trait CollectableHeap {
+    /// Collects every block that `tracer` does not report as live.
+    fn collect<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError>;
+
+    /// Performs the cheapest collection the heap offers, which may leave unreachable blocks
+    /// in older generations. Heaps without generations perform a full collection.
+    fn collect_minor<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError> {
+        self.collect(tracer)
+    }
+}
+
+impl CollectionSummary {
+    fn new(before: (usize, usize), after: (usize, usize)) -> Self {
+        Self {
+            blocks_reclaimed: before.0 - after.0,
+            words_reclaimed: before.1 - after.1,
+            blocks_live: after.0,
+            words_live: after.1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CollectionTrigger {
+    /// `malloc` found no unused block number.
+    OutOfBlocks,
+    /// `malloc` found no room in the heap.
+    OutOfMemory,
+    /// The application called `collect` or `collect_minor`.
+    Explicit,
+    /// `malloc` collected because the heap is in a stress mode.
+    Stress,
+    /// `malloc` collected because the heap's `TriggerPolicy` asked it to.
+    Policy,
+}
+
+/// Debugging aid for finding roots that a `Tracer` fails to report.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum StressMode {
+    #[default]
+    Off,
+    /// Perform a full collection at the start of every `malloc`.
+    Collect,
+    /// As `Collect`, and also overwrite every vacated semispace with `POISON`.
+    CollectAndPoison,
+}
+
+/// Fills memory vacated by a collection when poisoning is enabled. The high bit
+/// is clear, so poisoned words are never mistaken for encoded pointers.
+pub const POISON: u64 = 0x0BAD_F00D_0BAD_F00D;
+
+/// Statistics for a single collection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CollectionRecord {
+    pub trigger: CollectionTrigger,
+    pub blocks_live: usize,
+    pub words_copied: usize,
+    pub words_reclaimed: usize,
+    pub promotions: usize,
+    /// Whether generation 1 was collected along with generation 0.
+    pub gen_1_collected: bool,
+    /// Dirty cards a minor collection scanned for references into generation 0.
+    pub cards_scanned: usize,
+}
+
+/// Running totals over every collection a heap has performed.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CollectionTotals {
+    pub collections: usize,
+    pub words_copied: usize,
+    pub words_reclaimed: usize,
+    pub promotions: usize,
+    pub gen_1_collections: usize,
+    pub cards_scanned: usize,
+}
+
+const GC_STATS_HISTORY: usize = 16;
+
+/// Keeps the most recent `GC_STATS_HISTORY` collection records along with cumulative totals.
+#[derive(Copy, Clone, Debug)]
+pub struct GcStats {
+    records: [Option<CollectionRecord>; GC_STATS_HISTORY],
+    totals: CollectionTotals,
+}
+
+impl Default for GcStats {
+    fn default() -> Self {
+        Self {
+            records: [None; GC_STATS_HISTORY],
+            totals: CollectionTotals::default(),
+        }
+    }
+}
+
+impl GcStats {
+    fn record(&mut self, record: CollectionRecord) {
+        self.records[self.totals.collections % GC_STATS_HISTORY] = Some(record);
+        self.totals.collections += 1;
+        self.totals.words_copied += record.words_copied;
+        self.totals.words_reclaimed += record.words_reclaimed;
+        self.totals.promotions += record.promotions;
+        self.totals.cards_scanned += record.cards_scanned;
+        if record.gen_1_collected {
+            self.totals.gen_1_collections += 1;
+        }
+    }
+
+    pub fn totals(&self) -> CollectionTotals {
+        self.totals
+    }
+
+    pub fn last(&self) -> Option<CollectionRecord> {
+        self.records().last()
+    }
+
+    /// The retained records, oldest first.
+    pub fn records(&self) -> impl Iterator<Item = CollectionRecord> + '_ {
+        let start = self.totals.collections.saturating_sub(GC_STATS_HISTORY);
+        (start..self.totals.collections).filter_map(|i| self.records[i % GC_STATS_HISTORY])
+    }
+}
+
+/// How `RamHeap` chooses among the free holes that can satisfy a request.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FitPolicy {
+    /// The lowest-addressed hole that is large enough.
+    #[default]
+    FirstFit,
+    /// The smallest hole that is large enough.
+    BestFit,
+    /// The first hole large enough at or after the end of the previous allocation, wrapping around.
+    NextFit,
+}
+
+// Free holes are threaded through the heap itself: the first word of each hole
+// stores its size in the upper half and the address of the next hole in the lower half.
+const NO_HOLE: u64 = u32::MAX as u64;
+
+// With the `poison` feature, words that have never been allocated, or that a
+// collection has cleared, hold `POISON` so that reading them can be detected.
+const UNUSED_WORD: u64 = if cfg!(feature = "poison") { POISON } else { 0 };
+
+type FixedRamHeap<const HEAP_SIZE: usize> = RamHeap<[u64; HEAP_SIZE]>;
+
+// The words may be an array owned by the heap or a slice supplied by the caller.
+#[derive(Copy, Clone, Debug)]
+struct RamHeap<W> {
+    heap: W,
     next_address: usize,
+    free_list: Option<usize>,
+    fit_policy: FitPolicy,
+    next_fit: usize,
 }
 
-impl<const HEAP_SIZE: usize> RamHeap<HEAP_SIZE> {
+impl<const HEAP_SIZE: usize> RamHeap<[u64; HEAP_SIZE]> {
     fn new() -> Self {
+        Self::in_words([UNUSED_WORD; HEAP_SIZE])
+    }
+}
+
+impl<W: AsRef<[u64]> + AsMut<[u64]>> RamHeap<W> {
+    fn in_words(mut heap: W) -> Self {
+        heap.as_mut().fill(UNUSED_WORD);
         Self {
-            heap: [0; HEAP_SIZE],
+            heap,
             next_address: 0,
+            free_list: None,
+            fit_policy: FitPolicy::FirstFit,
+            next_fit: 0,
         }
     }
 
+    fn size(&self) -> usize {
+        self.heap.as_ref().len()
+    }
+
     fn clear(&mut self) {
-        self.next_address = 0;
+        self.truncate(0);
+    }
+
+    /// Discards the free list and everything at or above `next_address`.
+    fn truncate(&mut self, next_address: usize) {
+        #[cfg(feature = "poison")]
+        self.heap.as_mut()[next_address..self.next_address.max(next_address)].fill(POISON);
+        self.next_address = next_address;
+        self.free_list = None;
+        self.next_fit = 0;
+    }
+
+    fn hole_at(&self, address: usize) -> (usize, Option<usize>) {
+        let word = self.heap.as_ref()[address];
+        let next = word & NO_HOLE;
+        (
+            (word >> 32) as usize,
+            if next == NO_HOLE { None } else { Some(next as usize) },
+        )
+    }
+
+    fn set_hole(&mut self, address: usize, size: usize, next: Option<usize>) {
+        self.heap.as_mut()[address] = ((size as u64) << 32) | next.map_or(NO_HOLE, |n| n as u64);
+    }
+
+    fn set_next_hole(&mut self, prev: Option<usize>, next: Option<usize>) {
+        match prev {
+            None => self.free_list = next,
+            Some(prev) => {
+                let (size, _) = self.hole_at(prev);
+                self.set_hole(prev, size, next);
+            }
+        }
+    }
+
+    fn holes(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mut current = self.free_list;
+        core::iter::from_fn(move || {
+            let start = current?;
+            let (size, next) = self.hole_at(start);
+            current = next;
+            Some((start, size))
+        })
+    }
+
+    fn free(&mut self, start: usize, size: usize) {
+        let mut prev = None;
+        let mut current = self.free_list;
+        while let Some(hole) = current {
+            if hole > start {
+                break;
+            }
+            prev = current;
+            current = self.hole_at(hole).1;
+        }
+
+        let (mut start, mut size) = (start, size);
+        let mut next = current;
+        if let Some(hole) = next {
+            if start + size == hole {
+                let (hole_size, hole_next) = self.hole_at(hole);
+                size += hole_size;
+                next = hole_next;
+            }
+        }
+        if let Some(hole) = prev {
+            let (hole_size, _) = self.hole_at(hole);
+            if hole + hole_size == start {
+                start = hole;
+                size += hole_size;
+                prev = self.holes().take_while(|(h, _)| *h < hole).last().map(|(h, _)| h);
+            }
+        }
+
+        if start + size == self.next_address {
+            self.next_address = start;
+            self.set_next_hole(prev, None);
+        } else {
+            self.set_hole(start, size, next);
+            self.set_next_hole(prev, Some(start));
+        }
+    }
+
+    fn select_hole(&self, num_words: usize) -> Option<usize> {
+        let mut fits = self.holes().filter(|(_, size)| *size >= num_words);
+        match self.fit_policy {
+            FitPolicy::FirstFit => fits.next().map(|(start, _)| start),
+            FitPolicy::BestFit => fits.min_by_key(|(_, size)| *size).map(|(start, _)| start),
+            FitPolicy::NextFit => {
+                let mut wrapped = None;
+                for (start, _) in fits {
+                    if start >= self.next_fit {
+                        return Some(start);
+                    }
+                    wrapped = wrapped.or(Some(start));
+                }
+                wrapped
+            }
+        }
+    }
+
+    fn malloc_from_holes(&mut self, num_words: usize) -> Option<usize> {
+        let hole = self.select_hole(num_words)?;
+        let prev = self.holes().take_while(|(h, _)| *h < hole).last().map(|(h, _)| h);
+        let (size, next) = self.hole_at(hole);
+        if size > num_words {
+            self.set_hole(hole + num_words, size - num_words, next);
+            self.set_next_hole(prev, Some(hole + num_words));
+        } else {
+            self.set_next_hole(prev, next);
+        }
+        self.next_fit = hole + num_words;
+        Some(hole)
     }
 
     fn load(&self, address: usize) -> Result<u64, HeapError> {
@@ -134,16 +821,26 @@ impl<const HEAP_SIZE: usize> RamHeap<HEAP_SIZE> {
             return Err(HeapError::IllegalAddress(address, self.next_address));
         }
 
-        return Ok(self.heap[address]);
+        return Ok(self.heap.as_ref()[address]);
+    }
+
+    /// Like `load`, but reports a word that holds `POISON` when poisoning is enabled.
+    fn checked_load(&self, address: usize) -> Result<u64, LoadError> {
+        let value = self.load(address)?;
+        #[cfg(feature = "poison")]
+        if value == POISON {
+            return Err(LoadError::Poisoned(address));
+        }
+        Ok(value)
     }
 
     fn store(&mut self, address: usize, value: u64) -> Result<(), HeapError> {
         //todo!("Store value in heap at the given address. If address is illegal report it.");
-        if address > HEAP_SIZE || address < 0
+        if address >= self.size()
         {
-            return Err(HeapError::IllegalAddress(address, HEAP_SIZE));
+            return Err(HeapError::IllegalAddress(address, self.size()));
         }
-        self.heap[address] = value;
+        self.heap.as_mut()[address] = value;
         return Ok(());
     }
 
@@ -153,15 +850,21 @@ impl<const HEAP_SIZE: usize> RamHeap<HEAP_SIZE> {
         if num_words <= 0{
             return Err(HeapError::ZeroSizeRequest);
         }
+
+        if let Some(hole) = self.malloc_from_holes(num_words) {
+            self.zero_fill(hole, num_words);
+            return Ok(hole);
+        }
         
         let ad = self.next_address + num_words - 1;
-        if ad >= HEAP_SIZE{
+        if ad >= self.size(){
             return Err(HeapError::OutOfMemory);
         }
         
 
         let old = self.next_address;
         self.next_address =  ad + 1; 
+        self.zero_fill(old, num_words);
     
         return Ok(old);
         
@@ -173,17 +876,36 @@ impl<const HEAP_SIZE: usize> RamHeap<HEAP_SIZE> {
         // Otherwise, update `self.next_address` and return the address of the newly allocated memory.
     }
 
-    fn copy(&self, src: &BlockInfo, dest: &mut Self) -> Result<BlockInfo, HeapError> {
+    // With poisoning enabled, freshly allocated words read as zero, so that only words
+    // outside every block hold `POISON`.
+    fn zero_fill(&mut self, start: usize, num_words: usize) {
+        if cfg!(feature = "poison") {
+            self.heap.as_mut()[start..start + num_words].fill(0);
+        }
+    }
+
+    fn poison(&mut self) {
+        self.heap.as_mut().fill(POISON);
+    }
+
+    fn slide(&mut self, src: &BlockInfo, dest: usize) -> BlockInfo {
+        self.heap.as_mut().copy_within(src.start..src.start + src.size, dest);
+        BlockInfo { start: dest, size: src.size, num_times_copied: src.num_times_copied + 1 }
+    }
+
+    fn copy<D: AsRef<[u64]> + AsMut<[u64]>>(
+        &self,
+        src: &BlockInfo,
+        dest: &mut RamHeap<D>,
+    ) -> Result<BlockInfo, HeapError> {
         //todo!("Copy memory contents from src to dest");
         let d = match dest.malloc(src.size){
             Err(e) => return Err(e),
             Ok(ad) => ad,
         };
 
-        for i in 0..src.size{
-            let value = self.load(src.start + i)?;
-            dest.store(d + i, value)?;
-        }
+        let words = &self.heap.as_ref()[src.start..src.start + src.size];
+        dest.heap.as_mut()[d..d + src.size].copy_from_slice(words);
 
         return Ok(BlockInfo { start: d, size: src.size, num_times_copied: src.num_times_copied + 1 });
         // Outline
@@ -194,614 +916,4674 @@ impl<const HEAP_SIZE: usize> RamHeap<HEAP_SIZE> {
     }
 }
 
-pub struct OnceAndDoneHeap<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> {
-    heap: RamHeap<HEAP_SIZE>,
-    block_info: BlockTable<MAX_BLOCKS>,
+const NUM_SIZE_CLASSES: usize = 4;
+
+// Requests of up to NUM_SIZE_CLASSES words are recycled through a per-size free list,
+// each node storing the address of the next freed block of that size in its first word.
+#[derive(Copy, Clone, Debug)]
+struct SizeClassRamHeap<const HEAP_SIZE: usize> {
+    region: FixedRamHeap<HEAP_SIZE>,
+    size_classes: [Option<usize>; NUM_SIZE_CLASSES],
 }
 
-impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> GarbageCollectingHeap
-    for OnceAndDoneHeap<HEAP_SIZE, MAX_BLOCKS>
-{
+impl<const HEAP_SIZE: usize> SizeClassRamHeap<HEAP_SIZE> {
     fn new() -> Self {
         Self {
-            heap: RamHeap::new(),
-            block_info: BlockTable::new(),
+            region: RamHeap::new(),
+            size_classes: [None; NUM_SIZE_CLASSES],
         }
     }
 
-    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
-        self.block_info.address(p)
+    fn load(&self, address: usize) -> Result<u64, HeapError> {
+        self.region.load(address)
     }
 
-    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
-        self.block_info
-            .address(p)
-            .and_then(|address| self.heap.load(address))
+    fn checked_load(&self, address: usize) -> Result<u64, LoadError> {
+        self.region.checked_load(address)
     }
 
-    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
-        self.block_info
-            .address(p)
-            .and_then(|address| self.heap.store(address, value))
+    fn store(&mut self, address: usize, value: u64) -> Result<(), HeapError> {
+        self.region.store(address, value)
     }
 
-    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
-        self.block_info.blocks_in_use()
+    fn malloc(&mut self, num_words: usize) -> Result<usize, HeapError> {
+        if num_words > 0 && num_words <= NUM_SIZE_CLASSES {
+            if let Some(start) = self.size_classes[num_words - 1] {
+                let next = self.region.heap[start];
+                self.size_classes[num_words - 1] =
+                    if next == NO_HOLE { None } else { Some(next as usize) };
+                return Ok(start);
+            }
+        }
+        self.region.malloc(num_words)
     }
 
-    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
-        self.block_info.allocated_block_ptr(block)
+    fn free(&mut self, start: usize, size: usize) {
+        if size <= NUM_SIZE_CLASSES {
+            self.region.heap[start] = self.size_classes[size - 1].map_or(NO_HOLE, |n| n as u64);
+            self.size_classes[size - 1] = Some(start);
+        } else {
+            self.region.free(start, size);
+        }
     }
 
-    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
-        self.block_info.blocks_num_copies()
+    fn size_class(&self, size: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut current = self.size_classes[size - 1];
+        core::iter::from_fn(move || {
+            let start = current?;
+            let next = self.region.heap[start];
+            current = if next == NO_HOLE { None } else { Some(next as usize) };
+            Some(start)
+        })
     }
+}
 
-    fn malloc<T: Tracer>(&mut self, num_words: usize, _: &T) -> Result<Pointer, HeapError> {
-        match self.block_info.available_block() {
-            Some(block_num) => {
-                let start = self.heap.malloc(num_words)?;
-                self.block_info[block_num] = Some(BlockInfo {
-                    start,
-                    size: num_words,
-                    num_times_copied: 0,
-                });
-                Ok(Pointer::new(block_num, num_words))
-            }
-            None => Err(HeapError::OutOfBlocks),
-        }
-    }
+const MAX_ORDER: usize = usize::BITS as usize;
 
-    fn assert_no_strays(&self) {}
+// Free chunks of 2^order words are kept on one list per order, each node storing
+// the address of the next free chunk of that order in its first word.
+#[derive(Copy, Clone, Debug)]
+struct BuddyRamHeap<const HEAP_SIZE: usize> {
+    heap: [u64; HEAP_SIZE],
+    free_lists: [Option<usize>; MAX_ORDER],
+    words_requested: usize,
+    words_allocated: usize,
 }
 
-pub struct CopyingHeap<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> {
-    heaps: [RamHeap<HEAP_SIZE>; 2],
-    block_info: BlockTable<MAX_BLOCKS>,
-    active_heap: usize,
-}
+impl<const HEAP_SIZE: usize> BuddyRamHeap<HEAP_SIZE> {
+    fn new() -> Self {
+        let mut result = Self {
+            heap: [UNUSED_WORD; HEAP_SIZE],
+            free_lists: [None; MAX_ORDER],
+            words_requested: 0,
+            words_allocated: 0,
+        };
+        result.clear();
+        result
+    }
 
-impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> CopyingHeap<HEAP_SIZE, MAX_BLOCKS> {
-    fn collect<T: Tracer>(&mut self, tracer: &T) -> Result<(), HeapError> {
-        // These lines are helpful for avoiding borrow checker problems with arrays.
-        let inactive = (self.active_heap + 1) % 2;
-        let (src, dest) =
-            independent_elements_from(self.active_heap, inactive, &mut self.heaps).unwrap();
-       
-        let mut blocks = [false; MAX_BLOCKS];
-        tracer.trace(&mut blocks);
+    fn order_for(num_words: usize) -> usize {
+        num_words.next_power_of_two().trailing_zeros() as usize
+    }
 
-        for i in 0..MAX_BLOCKS{
-            if blocks[i]{
-                let block = src.copy(&self.block_info[i].unwrap(), dest)?;
-                self.block_info[i] = Some(block);
-            }
-            else{
-                self.block_info[i] = None;
+    fn clear(&mut self) {
+        #[cfg(feature = "poison")]
+        self.heap.fill(POISON);
+        self.free_lists = [None; MAX_ORDER];
+        self.words_requested = 0;
+        self.words_allocated = 0;
+
+        // A HEAP_SIZE that is not a power of two is carved into the largest aligned chunks that fit.
+        let mut start = 0;
+        while start < HEAP_SIZE {
+            let mut order = if start == 0 { MAX_ORDER - 1 } else { start.trailing_zeros() as usize };
+            while start + (1 << order) > HEAP_SIZE {
+                order -= 1;
             }
+            self.push(order, start);
+            start += 1 << order;
         }
+    }
 
-        self.heaps[self.active_heap].clear();
-        self.active_heap = inactive;
+    fn push(&mut self, order: usize, start: usize) {
+        self.heap[start] = self.free_lists[order].map_or(NO_HOLE, |n| n as u64);
+        self.free_lists[order] = Some(start);
+    }
 
-        Ok(())
-      
+    fn pop(&mut self, order: usize) -> Option<usize> {
+        let start = self.free_lists[order]?;
+        let next = self.heap[start];
+        self.free_lists[order] = if next == NO_HOLE { None } else { Some(next as usize) };
+        Some(start)
     }
-}
 
-impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> GarbageCollectingHeap
-    for CopyingHeap<HEAP_SIZE, MAX_BLOCKS>
-{
-    fn new() -> Self {
-        Self {
-            heaps: [RamHeap::new(); 2],
-            block_info: BlockTable::new(),
-            active_heap: 0,
+    fn remove(&mut self, order: usize, start: usize) -> bool {
+        let mut prev: Option<usize> = None;
+        let mut current = self.free_lists[order];
+        while let Some(chunk) = current {
+            let next = self.heap[chunk];
+            if chunk == start {
+                match prev {
+                    None => {
+                        self.free_lists[order] = if next == NO_HOLE { None } else { Some(next as usize) }
+                    }
+                    Some(prev) => self.heap[prev] = next,
+                }
+                return true;
+            }
+            prev = current;
+            current = if next == NO_HOLE { None } else { Some(next as usize) };
         }
+        false
     }
 
-    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
-        self.block_info.address(p)
+    fn load(&self, address: usize) -> Result<u64, HeapError> {
+        if address >= HEAP_SIZE {
+            return Err(HeapError::IllegalAddress(address, HEAP_SIZE));
+        }
+        Ok(self.heap[address])
     }
 
-    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
-        self.block_info
-            .address(p)
-            .and_then(|address| self.heaps[self.active_heap].load(address))
+    fn checked_load(&self, address: usize) -> Result<u64, LoadError> {
+        let value = self.load(address)?;
+        #[cfg(feature = "poison")]
+        if value == POISON {
+            return Err(LoadError::Poisoned(address));
+        }
+        Ok(value)
     }
 
-    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
-        self.block_info
-            .address(p)
-            .and_then(|address| self.heaps[self.active_heap].store(address, value))
+    fn store(&mut self, address: usize, value: u64) -> Result<(), HeapError> {
+        if address >= HEAP_SIZE {
+            return Err(HeapError::IllegalAddress(address, HEAP_SIZE));
+        }
+        self.heap[address] = value;
+        Ok(())
     }
 
-    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
-        self.block_info.blocks_in_use()
+    fn malloc(&mut self, num_words: usize) -> Result<usize, HeapError> {
+        if num_words == 0 {
+            return Err(HeapError::ZeroSizeRequest);
+        }
+        let order = Self::order_for(num_words);
+        let mut available = (order..MAX_ORDER)
+            .find(|o| self.free_lists[*o].is_some())
+            .ok_or(HeapError::OutOfMemory)?;
+        let start = self.pop(available).unwrap();
+        while available > order {
+            available -= 1;
+            self.push(available, start + (1 << available));
+        }
+        self.words_requested += num_words;
+        self.words_allocated += 1 << order;
+        if cfg!(feature = "poison") {
+            self.heap[start..start + num_words].fill(0);
+        }
+        Ok(start)
     }
 
-    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
-        self.block_info.allocated_block_ptr(block)
+    fn free(&mut self, start: usize, size: usize) {
+        let mut order = Self::order_for(size);
+        self.words_requested -= size;
+        self.words_allocated -= 1 << order;
+        #[cfg(feature = "poison")]
+        self.heap[start..start + (1 << order)].fill(POISON);
+
+        let mut start = start;
+        while order + 1 < MAX_ORDER {
+            let buddy = start ^ (1 << order);
+            if buddy + (1 << order) > HEAP_SIZE || !self.remove(order, buddy) {
+                break;
+            }
+            start = start.min(buddy);
+            order += 1;
+        }
+        self.push(order, start);
     }
 
-    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
-        self.block_info.blocks_num_copies()
+    fn internal_fragmentation(&self) -> usize {
+        self.words_allocated - self.words_requested
     }
 
-    fn malloc<T: Tracer>(
-        &mut self,
-        num_words: usize,
-        tracer: &T,
-    ) -> Result<Pointer, HeapError> {
-       
-        if num_words == 0{
-            return Err(HeapError::ZeroSizeRequest);
-        }
-        let avail_block = match self.block_info.available_block(){
-            None =>{
-                self.collect(tracer)?; 
-                match self.block_info.available_block(){
-                    None => return Err(HeapError::OutOfBlocks),
-                    Some(b) => b
-                }   
-            }
-            Some(b) => b
-        };
+    fn free_chunks(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..MAX_ORDER).flat_map(move |order| {
+            let mut current = self.free_lists[order];
+            core::iter::from_fn(move || {
+                let start = current?;
+                let next = self.heap[start];
+                current = if next == NO_HOLE { None } else { Some(next as usize) };
+                Some((start, 1 << order))
+            })
+        })
+    }
+}
 
-        let ad = match self.heaps[self.active_heap].malloc(num_words) {
-            Err(_) =>{
-                self.collect(tracer)?;
-                match self.heaps[self.active_heap].malloc(num_words) {
-                    Err(e) => return Err(HeapError::OutOfMemory),
-                    Ok(a) => a
-                }
-            }
-            Ok(a) => a
-        };
+/// Receives allocation and collection events from `OnceAndDoneHeap`, `CopyingHeap`
+/// and `GenerationalHeap`. Every method defaults to doing nothing.
+pub trait HeapObserver: Default {
+    fn on_malloc(&mut self, _block: usize, _num_words: usize) {}
+    fn on_collect_start(&mut self) {}
+    fn on_block_copied(&mut self, _block: usize, _num_words: usize) {}
+    fn on_block_freed(&mut self, _block: usize, _num_words: usize) {}
+    fn on_promote(&mut self, _block: usize, _num_words: usize) {}
+    fn on_collect_end(&mut self, _summary: CollectionSummary) {}
+}
 
-        self.block_info[avail_block] = Some(BlockInfo{start: ad, size: num_words, num_times_copied: 0});
+/// The default observer, which ignores every event.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoObserver;
 
-        return Ok(Pointer::new(avail_block, num_words));
-       
+impl HeapObserver for NoObserver {}
+
+/// How full a heap is at the start of a `malloc`. For `GenerationalHeap` the words are
+/// those of generation 0.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HeapOccupancy {
+    pub blocks_in_use: usize,
+    pub max_blocks: usize,
+    pub words_in_use: usize,
+    pub heap_size: usize,
+}
+
+/// Consulted by `CopyingHeap` and `GenerationalHeap` at the start of every `malloc` to
+/// decide whether to collect before allocating, and by `IncrementalCopyingHeap` to decide
+/// whether to start a collection. A heap still collects when an allocation fails,
+/// whatever its policy says.
+pub trait TriggerPolicy: Default {
+    fn should_collect(&self, occupancy: HeapOccupancy, num_words: usize) -> bool;
+
+    /// Called after every successful allocation.
+    fn on_malloc(&mut self, _num_words: usize) {}
+
+    /// Called at the end of every collection, however it was triggered.
+    fn on_collect(&mut self) {}
+}
+
+/// The default policy: collect only when an allocation fails.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CollectOnFailure;
+
+impl TriggerPolicy for CollectOnFailure {
+    fn should_collect(&self, _: HeapOccupancy, _: usize) -> bool {
+        false
+    }
+}
+
+/// Collects once a request would leave the heap, or its block table, more than
+/// `percent` full.
+#[derive(Copy, Clone, Debug)]
+pub struct OccupancyTrigger {
+    pub percent: usize,
+}
+
+impl Default for OccupancyTrigger {
+    fn default() -> Self {
+        Self { percent: 75 }
     }
+}
 
-    fn assert_no_strays(&self) {
-        assert!(self.heaps[(self.active_heap + 1) % 2].next_address == 0);
+impl TriggerPolicy for OccupancyTrigger {
+    fn should_collect(&self, occupancy: HeapOccupancy, num_words: usize) -> bool {
+        (occupancy.words_in_use + num_words) * 100 > occupancy.heap_size * self.percent
+            || (occupancy.blocks_in_use + 1) * 100 > occupancy.max_blocks * self.percent
     }
 }
 
+/// Collects once `allocations` blocks have been allocated since the last collection.
 #[derive(Copy, Clone, Debug)]
-pub struct GenerationalHeap<
-    const HEAP_SIZE: usize,
-    const MAX_BLOCKS: usize,
-    const MAX_COPIES: usize,
-> {
-    gen_0: [RamHeap<HEAP_SIZE>; 2],
-    gen_1: [RamHeap<HEAP_SIZE>; 2],
-    block_info: BlockTable<MAX_BLOCKS>,
-    active_gen_0: usize,
-    active_gen_1: usize,
+pub struct AllocationCountTrigger {
+    pub allocations: usize,
+    since_collection: usize,
 }
 
-impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, const MAX_COPIES: usize>
-    GenerationalHeap<HEAP_SIZE, MAX_BLOCKS, MAX_COPIES>
-{
-    fn active_inactive_gen_0_gen_1(
-        &mut self,
-    ) -> (
-        &mut RamHeap<HEAP_SIZE>,
-        &mut RamHeap<HEAP_SIZE>,
-        &mut RamHeap<HEAP_SIZE>,
-        &mut RamHeap<HEAP_SIZE>,
-        &mut BlockTable<MAX_BLOCKS>,
-    ) {
-        let inactive_0 = (self.active_gen_0 + 1) % 2;
-        let inactive_1 = (self.active_gen_1 + 1) % 2;
-        let (active_0, inactive_0) =
-            independent_elements_from(self.active_gen_0, inactive_0, &mut self.gen_0).unwrap();
-        let (active_1, inactive_1) =
-            independent_elements_from(self.active_gen_1, inactive_1, &mut self.gen_1).unwrap();
-        (
-            active_0,
-            inactive_0,
-            active_1,
-            inactive_1,
-            &mut self.block_info,
-        )
+impl AllocationCountTrigger {
+    pub fn new(allocations: usize) -> Self {
+        Self { allocations, since_collection: 0 }
     }
+}
 
-    fn heap_and_gen_for(&self, block_num: usize) -> Result<(usize, usize), HeapError> {
-        if block_num >= MAX_BLOCKS {
-            Err(HeapError::IllegalBlock(block_num, MAX_BLOCKS - 1))
-        } else {
-            match self.block_info[block_num] {
-                Some(block_info) => Ok(if block_info.num_times_copied > MAX_COPIES {
-                    (self.active_gen_1, 1)
-                } else {
-                    (self.active_gen_0, 0)
-                }),
-                None => Err(HeapError::UnallocatedBlock(block_num)),
-            }
-        }
+impl Default for AllocationCountTrigger {
+    fn default() -> Self {
+        Self::new(64)
     }
+}
 
-    fn collect_gen_0<T: Tracer>(&mut self, tracer: &T) -> Result<(), HeapError> {
-        let (active_0, inactive_0, active_1, inactive_1, block_info) =
-            self.active_inactive_gen_0_gen_1();
-        //todo!("Complete implementation.");
-        let mut gen_1_collected = false;
-        let mut used_blocks: [bool; MAX_BLOCKS] = [false; MAX_BLOCKS];
+impl TriggerPolicy for AllocationCountTrigger {
+    fn should_collect(&self, _: HeapOccupancy, _: usize) -> bool {
+        self.since_collection >= self.allocations
+    }
 
-        tracer.trace(&mut used_blocks);
-        for block in 0..MAX_BLOCKS{
-            if !used_blocks[block] && block_info[block].is_some(){
-                block_info[block] = None;
-            }
-        }
+    fn on_malloc(&mut self, _: usize) {
+        self.since_collection += 1;
+    }
 
-        for(block, &used) in used_blocks.iter().enumerate(){
-            if used{
-                if let Some(used_info) = block_info[block]{
-                    if used_info.num_times_copied == MAX_COPIES{
-                        if gen_1_collected{
-                            match active_0.copy(&used_info, inactive_1){
-                                Ok(new_info) => block_info[block] = Some(new_info),
-                                Err(e) => return Err(e)
-                            }
-                        }
-                        else {
-                            match active_0.copy(&used_info, active_1){
-                                Ok(new_info) => {block_info[block] = Some(new_info)},
-                                Err(_) => {
-                                    gen_1_collected = true;
-                                    Self::collect_gen_1(&used_blocks, block_info, active_1, inactive_1)?;
-                                    match active_0.copy(&used_info, inactive_1){
-                                        Ok(new_info) => block_info[block] = Some(new_info),
-                                        Err(e) => return Err(e)
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    else if used_info.num_times_copied < MAX_COPIES{
-                        let new_info = active_0.copy(&used_info, inactive_0)?;
-                        block_info[block] = Some(new_info);
-                    }
-                }
-            }
-            
-        }
-        active_0.clear();
-        self.active_gen_0 = (self.active_gen_0 + 1) % 2;
-        if gen_1_collected{
-            self.active_gen_1 = (self.active_gen_1 + 1) % 2;
-        }
-        return Ok(())
-        // Outline
-        //
-        // 1. Call the tracer to find out what blocks are in use.
-        // 2. For each block in use:
-        //    * If it has been copied MAX_COPIES times
-        //      * You'll need a variable to track whether you have already performed a generation 1 collection.
-        //      * If so, just return the error - multiple generation 1 collections will not be productive
-        //      * If not, copy into the active generation 1 heap.
-        //      * If that heap is out of space, perform a generation 1 collection by calling self.collect_gen_1().
-        //      * After the generation 1 collection, try copying it into the inactive generation 1 heap.
-        //    * If not, copy it into the inactive generation 0 heap.
-        // 3. Clear the active generation 0 heap.
-        // 4. Update self.active_gen_0 to the other heap.
-        // 5. If there was a generation 1 collection, update self.active_gen_1 to the other heap.
+    fn on_collect(&mut self) {
+        self.since_collection = 0;
     }
+}
 
-    fn collect_gen_1(
-        blocks_used: &[bool; MAX_BLOCKS],
-        block_info: &mut BlockTable<MAX_BLOCKS>,
-        src: &mut RamHeap<HEAP_SIZE>,
-        dest: &mut RamHeap<HEAP_SIZE>,
-    ) -> Result<(), HeapError> {
-        //todo!("Complete implementation.");
-        for (block, &used) in blocks_used.iter().enumerate(){
-            if used{
-                if let Some(used_info) = block_info[block] {
-                   if used_info.num_times_copied > MAX_COPIES{
-                        let new_info: BlockInfo = src.copy(&used_info, dest)?;
-                        block_info[block] = Some(new_info);
-                    } 
-                }
-                
-            }
-        }
-        src.clear();
-        Ok(())
-        // Outline
-        //
-        // 1. For each block in use:
-        //    * If it has been copied more than MAX_COPIES times, copy it to `dest`
-        // 2. Clear the `src` heap.
+/// Collects when a request would take the words allocated since the last collection past `words`.
+#[derive(Copy, Clone, Debug)]
+pub struct WordsAllocatedTrigger {
+    pub words: usize,
+    since_collection: usize,
+}
+
+impl WordsAllocatedTrigger {
+    pub fn new(words: usize) -> Self {
+        Self { words, since_collection: 0 }
     }
 }
 
-impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, const MAX_COPIES: usize> GarbageCollectingHeap
-    for GenerationalHeap<HEAP_SIZE, MAX_BLOCKS, MAX_COPIES>
-{
-    fn new() -> Self {
-        Self {
-            gen_0: [RamHeap::new(); 2],
-            gen_1: [RamHeap::new(); 2],
-            block_info: BlockTable::new(),
-            active_gen_0: 0,
-            active_gen_1: 0,
-        }
+impl Default for WordsAllocatedTrigger {
+    fn default() -> Self {
+        Self::new(1024)
     }
+}
 
-    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
-        let (heap, gen) = self.heap_and_gen_for(p.block_num())?;
-        let address = self.block_info.address(p)?;
-        (if gen == 0 {
-            &self.gen_0[heap]
-        } else {
-            &self.gen_1[heap]
-        })
-        .load(address)
+impl TriggerPolicy for WordsAllocatedTrigger {
+    fn should_collect(&self, _: HeapOccupancy, num_words: usize) -> bool {
+        self.since_collection + num_words > self.words
     }
 
-    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
-        let (heap, gen) = self.heap_and_gen_for(p.block_num())?;
-        let address = self.block_info.address(p)?;
-        (if gen == 0 {
-            &mut self.gen_0[heap]
-        } else {
-            &mut self.gen_1[heap]
-        })
-        .store(address, value)
+    fn on_malloc(&mut self, num_words: usize) {
+        self.since_collection += num_words;
     }
 
-    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
-        self.block_info.address(p)
+    fn on_collect(&mut self) {
+        self.since_collection = 0;
     }
+}
 
-    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
-        self.block_info.blocks_in_use()
+/// Allocates until its memory runs out and never reclaims anything. Its words are kept in
+/// `W` and its block table in `B`; see `OnceAndDoneHeap` and `SliceOnceAndDoneHeap`.
+pub struct OnceAndDoneHeapIn<W, B, O: HeapObserver = NoObserver> {
+    heap: RamHeap<W>,
+    block_info: BlockTable<B>,
+    observer: O,
+}
+
+pub type OnceAndDoneHeap<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, O = NoObserver> =
+    OnceAndDoneHeapIn<[u64; HEAP_SIZE], [BlockSlot; MAX_BLOCKS], O>;
+
+impl<W: Buffer<u64>, B: Buffer<BlockSlot>, O: HeapObserver> OnceAndDoneHeapIn<W, B, O> {
+    fn in_storage(words: W, blocks: B) -> Self {
+        Self {
+            heap: RamHeap::in_words(words),
+            block_info: BlockTable::in_slots(blocks),
+            observer: O::default(),
+        }
     }
 
-    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
-        self.block_info.allocated_block_ptr(block)
+    pub fn observer(&self) -> &O {
+        &self.observer
     }
 
-    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
-        self.block_info.blocks_num_copies()
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
     }
 
-    fn malloc<T: Tracer>(
+    pub fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        self.block_info.address(p)
+    }
+
+    pub fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heap.load(address))
+    }
+
+    pub fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heap.store(address, value))
+    }
+
+    pub fn blocks_in_use(&self) -> impl Iterator<Item = usize> + '_ {
+        self.block_info.blocks_in_use()
+    }
+
+    pub fn num_allocated_blocks(&self) -> usize {
+        self.block_info.num_allocated_blocks()
+    }
+
+    pub fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        self.block_info.allocated_block_ptr(block)
+    }
+
+    pub fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.block_info.blocks_num_copies()
+    }
+
+    pub fn malloc<T: Tracer>(&mut self, num_words: usize, _: &T) -> Result<Pointer, HeapError> {
+        match self.block_info.available_block() {
+            Some(block_num) => {
+                let start = self.heap.malloc(num_words)?;
+                self.block_info.set(block_num, Some(BlockInfo {
+                    start,
+                    size: num_words,
+                    num_times_copied: 0,
+                }));
+                self.observer.on_malloc(block_num, num_words);
+                Ok(Pointer::new(block_num, num_words))
+            }
+            None => Err(HeapError::OutOfBlocks),
+        }
+    }
+
+    pub fn assert_no_strays(&self) {}
+}
+
+// Only a heap that owns its memory can be built by `new`. Every method defers to the
+// inherent one of the same name, which works on any storage.
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, O: HeapObserver> GarbageCollectingHeap
+    for OnceAndDoneHeap<HEAP_SIZE, MAX_BLOCKS, O>
+{
+    fn new() -> Self {
+        Self::in_storage([0; HEAP_SIZE], [BlockSlot::default(); MAX_BLOCKS])
+    }
+
+    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        OnceAndDoneHeapIn::address(self, p)
+    }
+
+    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        OnceAndDoneHeapIn::load(self, p)
+    }
+
+    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        OnceAndDoneHeapIn::store(self, p, value)
+    }
+
+    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
+        OnceAndDoneHeapIn::blocks_in_use(self)
+    }
+
+    fn num_allocated_blocks(&self) -> usize {
+        OnceAndDoneHeapIn::num_allocated_blocks(self)
+    }
+
+    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        OnceAndDoneHeapIn::allocated_block_ptr(self, block)
+    }
+
+    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
+        OnceAndDoneHeapIn::blocks_num_copies(self)
+    }
+
+    fn malloc<T: Tracer>(&mut self, num_words: usize, tracer: &T) -> Result<Pointer, HeapError> {
+        OnceAndDoneHeapIn::malloc(self, num_words, tracer)
+    }
+
+    fn assert_no_strays(&self) {
+        OnceAndDoneHeapIn::assert_no_strays(self)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, O: HeapObserver> EpochCheckedHeap
+    for OnceAndDoneHeap<HEAP_SIZE, MAX_BLOCKS, O>
+{
+    fn block_epoch(&self, block: usize) -> Option<usize> {
+        self.block_info.epoch(block)
+    }
+}
+
+impl<W: Buffer<u64>, B: Buffer<BlockSlot>, O: HeapObserver> PoisonCheckedHeap for OnceAndDoneHeapIn<W, B, O> {
+    fn poison_checked_load(&self, p: Pointer) -> Result<u64, LoadError> {
+        self.heap.checked_load(self.block_info.address(p)?)
+    }
+}
+
+impl<W: Buffer<u64>, B: Buffer<BlockSlot>, O: HeapObserver> CollectableHeap for OnceAndDoneHeapIn<W, B, O> {
+    /// Never reclaims anything; provided so every heap can be collected the same way.
+    fn collect<T: Tracer>(&mut self, _: &T) -> Result<CollectionSummary, HeapError> {
+        self.observer.on_collect_start();
+        let usage = self.block_info.usage();
+        let summary = CollectionSummary::new(usage, usage);
+        self.observer.on_collect_end(summary);
+        Ok(summary)
+    }
+}
+
+impl<W: Buffer<u64>, B: Buffer<BlockSlot>, O: HeapObserver> VerifiableHeap for OnceAndDoneHeapIn<W, B, O> {
+    fn verify(&self) -> Violations {
+        self.block_info.verify(0, |_, _| (0, self.heap.next_address))
+    }
+}
+
+/// The order in which `CopyingHeap` evacuates blocks reachable from the roots.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ScanOrder {
+    /// Cheney's algorithm: a scan pointer sweeps to-space, copying referents as it finds them.
+    #[default]
+    BreadthFirst,
+    /// Blocks are copied in depth-first order, so each block is followed by its descendants.
+    DepthFirst,
+}
+
+/// What `CopyingHeap` keeps in the block table for each block. Its fields are private.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CopyingSlot {
+    // Set once the current collection has copied this block.
+    copied: bool,
+    // When scanning depth-first, the blocks being scanned form a stack threaded through
+    // these, each with the offset of its next word to scan.
+    next_scan: usize,
+    scan_offset: usize,
+}
+
+/// A semispace copying collector. Its semispaces are kept in `W`, its block table in `B`,
+/// and in `M` the marks that a `Tracer` fills, one per block; see `CopyingHeap`,
+/// `SliceCopyingHeap` and `VecCopyingHeap`.
+pub struct CopyingHeapIn<
+    W,
+    B,
+    M,
+    O: HeapObserver = NoObserver,
+    P: TriggerPolicy = CollectOnFailure,
+> {
+    heaps: [RamHeap<W>; 2],
+    block_info: BlockTable<B, CopyingSlot>,
+    marks: M,
+    active_heap: usize,
+    scan_order: ScanOrder,
+    stress_mode: StressMode,
+    stats: GcStats,
+    observer: O,
+    trigger_policy: P,
+    growth: Option<GrowthPolicy>,
+}
+
+pub type CopyingHeap<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, O = NoObserver, P = CollectOnFailure> =
+    CopyingHeapIn<[u64; HEAP_SIZE], [BlockSlot<CopyingSlot>; MAX_BLOCKS], [bool; MAX_BLOCKS], O, P>;
+
+impl<W: Buffer<u64>, B: Buffer<BlockSlot<CopyingSlot>>, M: Buffer<bool>, O: HeapObserver, P: TriggerPolicy>
+    CopyingHeapIn<W, B, M, O, P>
+{
+    fn in_storage(words: [W; 2], blocks: B, marks: M) -> Self {
+        assert_eq!(blocks.as_ref().len(), marks.as_ref().len());
+        let [first, second] = words;
+        Self {
+            heaps: [RamHeap::in_words(first), RamHeap::in_words(second)],
+            block_info: BlockTable::in_slots(blocks),
+            marks,
+            active_heap: 0,
+            scan_order: ScanOrder::BreadthFirst,
+            stress_mode: StressMode::Off,
+            stats: GcStats::default(),
+            observer: O::default(),
+            trigger_policy: P::default(),
+            growth: None,
+        }
+    }
+
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    pub fn set_scan_order(&mut self, scan_order: ScanOrder) {
+        self.scan_order = scan_order;
+    }
+
+    pub fn trigger_policy(&self) -> &P {
+        &self.trigger_policy
+    }
+
+    pub fn set_trigger_policy(&mut self, trigger_policy: P) {
+        self.trigger_policy = trigger_policy;
+    }
+
+    fn occupancy(&self) -> HeapOccupancy {
+        HeapOccupancy {
+            blocks_in_use: self.block_info.num_allocated_blocks(),
+            max_blocks: self.block_info.len(),
+            words_in_use: self.heaps[self.active_heap].next_address,
+            heap_size: self.heaps[self.active_heap].size(),
+        }
+    }
+
+    pub fn set_stress_mode(&mut self, stress_mode: StressMode) {
+        self.stress_mode = stress_mode;
+    }
+
+    pub fn set_growth_policy(&mut self, growth: Option<GrowthPolicy>) {
+        self.growth = growth;
+    }
+
+    pub fn stats(&self) -> &GcStats {
+        &self.stats
+    }
+
+    fn collect_for<T: Tracer>(
+        &mut self,
+        tracer: &T,
+        trigger: CollectionTrigger,
+    ) -> Result<CollectionSummary, HeapError> {
+        let before = self.block_info.usage();
+        let inactive = (self.active_heap + 1) % 2;
+        // The survivors are at most every block in use, so once those are known to fit in
+        // to-space nothing can fail. To-space is normally empty and as large as from-space,
+        // so this only fails if it holds stray words.
+        if before.1 > self.heaps[inactive].size() - self.heaps[inactive].next_address {
+            return Err(HeapError::OutOfMemory);
+        }
+        self.observer.on_collect_start();
+        self.copy_live(tracer);
+
+        for i in 0..self.block_info.len() {
+            if !self.block_info.collector(i).copied {
+                if let Some(info) = self.block_info[i] {
+                    self.observer.on_block_freed(i, info.size);
+                }
+                self.block_info.set(i, None);
+            }
+        }
+
+        self.heaps[self.active_heap].clear();
+        if self.stress_mode == StressMode::CollectAndPoison {
+            self.heaps[self.active_heap].poison();
+        }
+        self.active_heap = inactive;
+
+        let summary = CollectionSummary::new(before, self.block_info.usage());
+        self.stats.record(CollectionRecord {
+            trigger,
+            blocks_live: summary.blocks_live,
+            words_copied: summary.words_live,
+            words_reclaimed: summary.words_reclaimed,
+            promotions: 0,
+            gen_1_collected: false,
+            cards_scanned: 0,
+        });
+        self.trigger_policy.on_collect();
+        self.observer.on_collect_end(summary);
+        Ok(summary)
+      
+    }
+
+    /// Copies every block reachable from the roots into to-space, marking each one it copies.
+    fn copy_live<T: Tracer>(&mut self, tracer: &T) {
+        // These lines are helpful for avoiding borrow checker problems with arrays.
+        let inactive = (self.active_heap + 1) % 2;
+        let (src, dest) =
+            independent_elements_from(self.active_heap, inactive, &mut self.heaps).unwrap();
+       
+        let blocks = self.marks.as_mut();
+        blocks.fill(false);
+        tracer.trace(blocks);
+
+        let block_info = &mut self.block_info;
+        for block in 0..block_info.len() {
+            block_info.collector_mut(block).copied = false;
+        }
+        for (root, &marked) in blocks.iter().enumerate() {
+            if !marked || block_info.collector(root).copied {
+                continue;
+            }
+            Self::evacuate(root, block_info, src, dest, &mut self.observer);
+
+            if self.scan_order == ScanOrder::DepthFirst {
+                let mut top = root;
+                block_info.collector_mut(root).next_scan = block_info.len();
+                while top < block_info.len() {
+                    let (info, offset) = (block_info[top].unwrap(), block_info.collector(top).scan_offset);
+                    if offset == info.size {
+                        top = block_info.collector(top).next_scan;
+                        continue;
+                    }
+                    block_info.collector_mut(top).scan_offset += 1;
+                    let word = dest.heap.as_ref()[info.start + offset];
+                    if let Some(child) = Self::referent(block_info, word) {
+                        Self::evacuate(child, block_info, src, dest, &mut self.observer);
+                        block_info.collector_mut(child).next_scan = top;
+                        top = child;
+                    }
+                }
+            }
+        }
+
+        if self.scan_order == ScanOrder::BreadthFirst {
+            // Everything between `scan` and `dest.next_address` has been copied but not yet scanned.
+            let mut scan = 0;
+            while scan < dest.next_address {
+                let word = dest.heap.as_ref()[scan];
+                if let Some(child) = Self::referent(block_info, word) {
+                    Self::evacuate(child, block_info, src, dest, &mut self.observer);
+                }
+                scan += 1;
+            }
+        }
+    }
+
+    fn evacuate(
+        block: usize,
+        block_info: &mut BlockTable<B, CopyingSlot>,
+        src: &RamHeap<W>,
+        dest: &mut RamHeap<W>,
+        observer: &mut O,
+    ) {
+        let info = src.copy(&block_info[block].unwrap(), dest).expect("the survivors fit in to-space");
+        block_info.set(block, Some(info));
+        let slot = block_info.collector_mut(block);
+        slot.copied = true;
+        slot.scan_offset = 0;
+        observer.on_block_copied(block, info.size);
+    }
+
+    fn referent(block_info: &BlockTable<B, CopyingSlot>, word: u64) -> Option<usize> {
+        decode_pointer(word)
+            .filter(|p| block_info.address(*p).is_ok() && !block_info.collector(p.block_num()).copied)
+            .map(|p| p.block_num())
+    }
+
+    /// Adds blocks to the table if the growth policy allows, returning the first one added.
+    fn grow_blocks(&mut self) -> Option<usize> {
+        let policy = self.growth?;
+        if policy.grow_blocks(&mut self.block_info, &mut self.marks) {
+            self.block_info.available_block()
+        } else {
+            None
+        }
+    }
+
+    /// Allocates `num_words` after growing both semispaces, if the growth policy allows.
+    fn grow_and_malloc(&mut self, num_words: usize) -> Result<usize, HeapError> {
+        let needed = self.heaps[self.active_heap].next_address + num_words;
+        match self.growth {
+            Some(policy) if policy.grow_words(&mut self.heaps, needed) => {
+                self.heaps[self.active_heap].malloc(num_words)
+            }
+            _ => Err(HeapError::OutOfMemory),
+        }
+    }
+
+    pub fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        self.block_info.address(p)
+    }
+
+    pub fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heaps[self.active_heap].load(address))
+    }
+
+    pub fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heaps[self.active_heap].store(address, value))
+    }
+
+    pub fn blocks_in_use(&self) -> impl Iterator<Item = usize> + '_ {
+        self.block_info.blocks_in_use()
+    }
+
+    pub fn num_allocated_blocks(&self) -> usize {
+        self.block_info.num_allocated_blocks()
+    }
+
+    pub fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        self.block_info.allocated_block_ptr(block)
+    }
+
+    pub fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.block_info.blocks_num_copies()
+    }
+
+    /// When a collection does not free enough, a heap with a growth policy grows instead
+    /// of failing.
+    pub fn malloc<T: Tracer>(
         &mut self,
         num_words: usize,
         tracer: &T,
     ) -> Result<Pointer, HeapError> {
-        //todo!("Implement generational malloc");
-        if num_words == 0 {
+       
+        if num_words == 0{
             return Err(HeapError::ZeroSizeRequest);
         }
-        let block = match self.block_info.available_block(){
-            Some(block) => block,
-            None => {
-                self.collect_gen_0(tracer)?;
+        if self.stress_mode != StressMode::Off {
+            self.collect_for(tracer, CollectionTrigger::Stress)?;
+        } else if self.trigger_policy.should_collect(self.occupancy(), num_words) {
+            // An early collection is only an optimisation, so the request may still
+            // succeed without it.
+            let _ = self.collect_for(tracer, CollectionTrigger::Policy);
+        }
+        let avail_block = match self.block_info.available_block(){
+            None =>{
+                self.collect_for(tracer, CollectionTrigger::OutOfBlocks)?; 
                 match self.block_info.available_block(){
-                    Some(block) => block,
-                    None => return Err(HeapError::OutOfBlocks)
-                }
+                    None => self.grow_blocks().ok_or(HeapError::OutOfBlocks)?,
+                    Some(b) => b
+                }   
             }
+            Some(b) => b
         };
-        let start = match self.gen_0[self.active_gen_0].malloc(num_words){
-            Ok(ad) => ad,
-            Err(_) => {
-                self.collect_gen_0(tracer)?;
-                match self.gen_0[self.active_gen_0].malloc(num_words){
-                    Ok(ad) => ad,
-                    Err(_) => return Err(HeapError::OutOfMemory)
+
+        let ad = match self.heaps[self.active_heap].malloc(num_words) {
+            Err(_) =>{
+                self.collect_for(tracer, CollectionTrigger::OutOfMemory)?;
+                match self.heaps[self.active_heap].malloc(num_words) {
+                    Err(_) => self.grow_and_malloc(num_words)?,
+                    Ok(a) => a
                 }
             }
+            Ok(a) => a
         };
 
-        self.block_info[block] = Some(BlockInfo{start, size: num_words, num_times_copied: 0});
-        Ok(Pointer::new(block, num_words))
-        // Outline
-        //
-        // 1. Find an available block number
-        //    * If none are available, perform a collection by calling self.collect_gen_0().
-        //    * If none are still available, report out of blocks.
-        // 2. Perform a generation zero malloc.
-        //    * If no space is available, perform a collection by calling self.collect_gen_0().
-        //    * If no space is still available, report out of memory.
-        // 3. Create entry in the block table for the newly allocated block.
-        // 4. Return a pointer to the newly allocated block.
+        self.block_info.set(avail_block, Some(BlockInfo{start: ad, size: num_words, num_times_copied: 0}));
+        self.observer.on_malloc(avail_block, num_words);
+        self.trigger_policy.on_malloc(num_words);
+
+        return Ok(Pointer::new(avail_block, num_words));
+       
+    }
+
+    pub fn assert_no_strays(&self) {
+        assert!(self.heaps[(self.active_heap + 1) % 2].next_address == 0);
+    }
+}
+
+// As with `OnceAndDoneHeap`, only the array-backed heap can be built by `new`.
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, O: HeapObserver, P: TriggerPolicy> GarbageCollectingHeap
+    for CopyingHeap<HEAP_SIZE, MAX_BLOCKS, O, P>
+{
+    fn new() -> Self {
+        Self::in_storage([[0; HEAP_SIZE]; 2], [BlockSlot::default(); MAX_BLOCKS], [false; MAX_BLOCKS])
+    }
+
+    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        CopyingHeapIn::address(self, p)
+    }
+
+    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        CopyingHeapIn::load(self, p)
+    }
+
+    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        CopyingHeapIn::store(self, p, value)
+    }
+
+    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
+        CopyingHeapIn::blocks_in_use(self)
+    }
+
+    fn num_allocated_blocks(&self) -> usize {
+        CopyingHeapIn::num_allocated_blocks(self)
+    }
+
+    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        CopyingHeapIn::allocated_block_ptr(self, block)
+    }
+
+    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
+        CopyingHeapIn::blocks_num_copies(self)
+    }
+
+    fn malloc<T: Tracer>(&mut self, num_words: usize, tracer: &T) -> Result<Pointer, HeapError> {
+        CopyingHeapIn::malloc(self, num_words, tracer)
+    }
+
+    fn assert_no_strays(&self) {
+        CopyingHeapIn::assert_no_strays(self)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, O: HeapObserver, P: TriggerPolicy> EpochCheckedHeap
+    for CopyingHeap<HEAP_SIZE, MAX_BLOCKS, O, P>
+{
+    fn block_epoch(&self, block: usize) -> Option<usize> {
+        self.block_info.epoch(block)
+    }
+}
+
+impl<W: Buffer<u64>, B: Buffer<BlockSlot<CopyingSlot>>, M: Buffer<bool>, O: HeapObserver, P: TriggerPolicy> PoisonCheckedHeap
+    for CopyingHeapIn<W, B, M, O, P>
+{
+    fn poison_checked_load(&self, p: Pointer) -> Result<u64, LoadError> {
+        self.heaps[self.active_heap].checked_load(self.block_info.address(p)?)
+    }
+}
+
+impl<W: Buffer<u64>, B: Buffer<BlockSlot<CopyingSlot>>, M: Buffer<bool>, O: HeapObserver, P: TriggerPolicy> CollectableHeap
+    for CopyingHeapIn<W, B, M, O, P>
+{
+    /// Copies every block reachable from `tracer` into the inactive semispace and makes it active.
+    fn collect<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError> {
+        self.collect_for(tracer, CollectionTrigger::Explicit)
+    }
+}
+
+impl<W: Buffer<u64>, B: Buffer<BlockSlot<CopyingSlot>>, M: Buffer<bool>, O: HeapObserver, P: TriggerPolicy> VerifiableHeap
+    for CopyingHeapIn<W, B, M, O, P>
+{
+    fn verify(&self) -> Violations {
+        let active = &self.heaps[self.active_heap];
+        let collections = self.stats.totals().collections;
+        let mut violations = self.block_info.verify(collections, |_, _| (0, active.next_address));
+        let inactive = &self.heaps[(self.active_heap + 1) % 2];
+        if inactive.next_address != 0 {
+            violations.push(Violation::Strays(inactive.next_address));
+        }
+        violations
+    }
+}
+
+/// Words an `IncrementalCopyingHeap` scans or copies per `malloc` unless told otherwise.
+const DEFAULT_WORK_PER_MALLOC: usize = 16;
+
+/// A Baker-style copying heap that spreads each collection across many calls to `malloc`.
+/// A collection flips the semispaces and evacuates the roots, after which every `malloc`
+/// scans and copies at most `work_per_malloc` words of to-space before allocating its
+/// block there. A collection starts when `P` asks for one or an allocation fails, and
+/// is finished all at once when to-space runs out of room.
+///
+/// Blocks still in from-space are forwarded on demand, so the mutator only ever sees
+/// to-space. `load` evacuates the block it reads and any block the word read points to,
+/// and `store` evacuates the block it writes and any block the stored word points to.
+/// Since `load` takes `&self`, everything evacuation changes is kept in a `RefCell`.
+/// A collection still ends by re-tracing the roots, in case the `Tracer` reports a
+/// block the mutator reached without a load, such as through `allocated_block_ptr`.
+///
+/// Evacuation never runs out of room: a collection starts with every live block fitting
+/// in the empty to-space, and `malloc` refuses to allocate into the words that blocks
+/// still in from-space will need, finishing the collection instead.
+pub struct IncrementalCopyingHeap<
+    const HEAP_SIZE: usize,
+    const MAX_BLOCKS: usize,
+    P: TriggerPolicy = CollectOnFailure,
+> {
+    spaces: RefCell<IncrementalSpaces<HEAP_SIZE, MAX_BLOCKS>>,
+    scan: usize,
+    work_per_malloc: usize,
+    stats: GcStats,
+    trigger_policy: P,
+}
+
+// What `IncrementalCopyingHeap` keeps in the block table for each block.
+#[derive(Copy, Clone, Debug, Default)]
+struct IncrementalSlot {
+    // Set once the collection in progress has moved this block to to-space.
+    evacuated: bool,
+}
+
+// The parts of an `IncrementalCopyingHeap` that evacuating a block changes.
+struct IncrementalSpaces<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> {
+    heaps: [FixedRamHeap<HEAP_SIZE>; 2],
+    block_info: FixedBlockTable<MAX_BLOCKS, IncrementalSlot>,
+    active_heap: usize,
+    // While a collection is in progress the active heap is to-space, holding the
+    // evacuated blocks; every other block is still in from-space.
+    in_progress: Option<CollectionTrigger>,
+    words_copied: usize,
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> IncrementalSpaces<HEAP_SIZE, MAX_BLOCKS> {
+    fn heap_of(&self, block: usize) -> usize {
+        if self.in_progress.is_some() && !self.block_info.collector(block).evacuated {
+            (self.active_heap + 1) % 2
+        } else {
+            self.active_heap
+        }
+    }
+
+    fn unevacuated(&self, word: u64) -> Option<usize> {
+        decode_pointer(word)
+            .filter(|p| self.block_info.address(*p).is_ok() && self.heap_of(p.block_num()) != self.active_heap)
+            .map(|p| p.block_num())
+    }
+
+    fn unevacuated_words(&self) -> usize {
+        self.block_info
+            .blocks_in_use()
+            .filter(|block| self.heap_of(*block) != self.active_heap)
+            .map(|block| self.block_info[block].unwrap().size)
+            .sum()
+    }
+
+    /// Copies `block` from from-space to to-space, returning its size. To-space always
+    /// has room, since `try_malloc` leaves enough for every block not yet evacuated.
+    fn evacuate(&mut self, block: usize) -> usize {
+        let inactive = (self.active_heap + 1) % 2;
+        let (src, dest) =
+            independent_elements_from(inactive, self.active_heap, &mut self.heaps).unwrap();
+        let info = src
+            .copy(&self.block_info[block].unwrap(), dest)
+            .expect("to-space is reserved for every unevacuated block");
+        self.block_info.set(block, Some(info));
+        self.block_info.collector_mut(block).evacuated = true;
+        self.words_copied += info.size;
+        info.size
+    }
+
+    /// Evacuates `block` if it is still in from-space.
+    fn forward(&mut self, block: usize) {
+        if self.heap_of(block) != self.active_heap {
+            self.evacuate(block);
+        }
+    }
+
+    /// Evacuates the block `word` points to if it is still in from-space.
+    fn forward_referent(&mut self, word: u64) {
+        if let Some(block) = self.unevacuated(word) {
+            self.evacuate(block);
+        }
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, P: TriggerPolicy>
+    IncrementalCopyingHeap<HEAP_SIZE, MAX_BLOCKS, P>
+{
+    pub fn set_work_per_malloc(&mut self, words: usize) {
+        self.work_per_malloc = words;
+    }
+
+    pub fn trigger_policy(&self) -> &P {
+        &self.trigger_policy
+    }
+
+    pub fn set_trigger_policy(&mut self, trigger_policy: P) {
+        self.trigger_policy = trigger_policy;
+    }
+
+    pub fn stats(&self) -> &GcStats {
+        &self.stats
+    }
+
+    pub fn is_collecting(&self) -> bool {
+        self.spaces.borrow().in_progress.is_some()
+    }
+
+    fn occupancy(&self) -> HeapOccupancy {
+        let spaces = self.spaces.borrow();
+        HeapOccupancy {
+            blocks_in_use: spaces.block_info.num_allocated_blocks(),
+            max_blocks: MAX_BLOCKS,
+            words_in_use: spaces.heaps[spaces.active_heap].next_address,
+            heap_size: HEAP_SIZE,
+        }
+    }
+
+    /// Flips the semispaces and evacuates the roots.
+    fn start<T: Tracer>(&mut self, tracer: &T, trigger: CollectionTrigger) {
+        let spaces = self.spaces.get_mut();
+        spaces.active_heap = (spaces.active_heap + 1) % 2;
+        spaces.in_progress = Some(trigger);
+        for block in 0..MAX_BLOCKS {
+            spaces.block_info.collector_mut(block).evacuated = false;
+        }
+        spaces.words_copied = 0;
+        self.scan = 0;
+        self.evacuate_roots(tracer);
+    }
+
+    /// Evacuates every root still in from-space, returning whether there were any.
+    fn evacuate_roots<T: Tracer>(&mut self, tracer: &T) -> bool {
+        let mut roots = [false; MAX_BLOCKS];
+        tracer.trace(&mut roots);
+        let spaces = self.spaces.get_mut();
+        let mut found = false;
+        for (block, &root) in roots.iter().enumerate() {
+            if root && spaces.block_info[block].is_some() && spaces.heap_of(block) != spaces.active_heap {
+                spaces.evacuate(block);
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// Scans up to `budget` words of to-space, with every word copied also counting
+    /// against it. Returns whether the scan has caught up with the end of to-space.
+    fn scan_some(&mut self, mut budget: usize) -> bool {
+        let spaces = self.spaces.get_mut();
+        while budget > 0 && self.scan < spaces.heaps[spaces.active_heap].next_address {
+            let word = spaces.heaps[spaces.active_heap].heap[self.scan];
+            self.scan += 1;
+            budget -= 1;
+            if let Some(block) = spaces.unevacuated(word) {
+                budget = budget.saturating_sub(spaces.evacuate(block));
+            }
+        }
+        self.scan == spaces.heaps[spaces.active_heap].next_address
+    }
+
+    /// Completes the collection in progress and frees every block left in from-space.
+    fn finish<T: Tracer>(&mut self, tracer: &T) -> CollectionSummary {
+        loop {
+            self.scan_some(usize::MAX);
+            if !self.evacuate_roots(tracer) {
+                break;
+            }
+        }
+
+        let spaces = self.spaces.get_mut();
+        let mut summary = CollectionSummary::default();
+        for block in 0..MAX_BLOCKS {
+            if let Some(info) = spaces.block_info[block] {
+                if !spaces.block_info.collector(block).evacuated {
+                    summary.blocks_reclaimed += 1;
+                    summary.words_reclaimed += info.size;
+                    spaces.block_info.set(block, None);
+                }
+            }
+        }
+        spaces.heaps[(spaces.active_heap + 1) % 2].clear();
+        (summary.blocks_live, summary.words_live) = spaces.block_info.usage();
+
+        self.stats.record(CollectionRecord {
+            trigger: spaces.in_progress.take().unwrap(),
+            blocks_live: summary.blocks_live,
+            words_copied: spaces.words_copied,
+            words_reclaimed: summary.words_reclaimed,
+            promotions: 0,
+            gen_1_collected: false,
+            cards_scanned: 0,
+        });
+        self.trigger_policy.on_collect();
+        summary
+    }
+
+    /// Allocates in the active semispace, leaving enough of to-space free for every
+    /// block that a collection in progress might still evacuate.
+    fn try_malloc(&mut self, num_words: usize) -> Result<Pointer, HeapError> {
+        let spaces = self.spaces.get_mut();
+        let block = match spaces.block_info.available_block() {
+            None => return Err(HeapError::OutOfBlocks),
+            Some(b) => b,
+        };
+        let reserved = if spaces.in_progress.is_some() { spaces.unevacuated_words() } else { 0 };
+        if spaces.heaps[spaces.active_heap].next_address + reserved + num_words > HEAP_SIZE {
+            return Err(HeapError::OutOfMemory);
+        }
+        let start = spaces.heaps[spaces.active_heap].malloc(num_words)?;
+        spaces.block_info.set(block, Some(BlockInfo { start, size: num_words, num_times_copied: 0 }));
+        spaces.block_info.collector_mut(block).evacuated = true;
+        self.trigger_policy.on_malloc(num_words);
+        Ok(Pointer::new(block, num_words))
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, P: TriggerPolicy> GarbageCollectingHeap
+    for IncrementalCopyingHeap<HEAP_SIZE, MAX_BLOCKS, P>
+{
+    fn new() -> Self {
+        Self {
+            spaces: RefCell::new(IncrementalSpaces {
+                heaps: [RamHeap::new(); 2],
+                block_info: BlockTable::new(),
+                active_heap: 0,
+                in_progress: None,
+                words_copied: 0,
+            }),
+            scan: 0,
+            work_per_malloc: DEFAULT_WORK_PER_MALLOC,
+            stats: GcStats::default(),
+            trigger_policy: P::default(),
+        }
+    }
+
+    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        self.spaces.borrow().block_info.address(p)
+    }
+
+    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        let mut spaces = self.spaces.borrow_mut();
+        spaces.block_info.address(p)?;
+        // The mutator may neither read from from-space nor pick up a reference into it.
+        spaces.forward(p.block_num());
+        let value = spaces.heaps[spaces.active_heap].load(spaces.block_info.address(p)?)?;
+        spaces.forward_referent(value);
+        Ok(value)
+    }
+
+    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        let spaces = self.spaces.get_mut();
+        spaces.block_info.address(p)?;
+        // Nothing may be written into from-space, nor may a block that has been
+        // scanned come to refer to one that is still there.
+        spaces.forward(p.block_num());
+        spaces.forward_referent(value);
+        spaces
+            .block_info
+            .address(p)
+            .and_then(|address| spaces.heaps[spaces.active_heap].store(address, value))
+    }
+
+    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
+        // Each step borrows the block table only briefly, so that loads may be made
+        // while iterating.
+        let mut from = 0;
+        core::iter::from_fn(move || {
+            let block = self.spaces.borrow().block_info.next_block(from, true)?;
+            from = block + 1;
+            Some(block)
+        })
+    }
+
+    fn num_allocated_blocks(&self) -> usize {
+        self.spaces.borrow().block_info.num_allocated_blocks()
+    }
+
+    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        self.spaces.borrow().block_info.allocated_block_ptr(block)
+    }
+
+    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
+        self.blocks_in_use()
+            .filter_map(|block| Some((block, self.spaces.borrow().block_info[block]?.num_times_copied)))
+    }
+
+    fn malloc<T: Tracer>(&mut self, num_words: usize, tracer: &T) -> Result<Pointer, HeapError> {
+        if num_words == 0 {
+            return Err(HeapError::ZeroSizeRequest);
+        }
+        if !self.is_collecting() && self.trigger_policy.should_collect(self.occupancy(), num_words) {
+            self.start(tracer, CollectionTrigger::Policy);
+        }
+        if self.is_collecting() && self.scan_some(self.work_per_malloc) {
+            self.finish(tracer);
+        }
+        if let Ok(p) = self.try_malloc(num_words) {
+            return Ok(p);
+        }
+
+        // Finish any collection in progress, since it may already have freed enough.
+        // Otherwise start a new one and allocate in to-space while it runs, falling
+        // back to finishing that one too.
+        if self.is_collecting() {
+            self.finish(tracer);
+            if let Ok(p) = self.try_malloc(num_words) {
+                return Ok(p);
+            }
+        }
+        let trigger = match self.spaces.get_mut().block_info.available_block() {
+            None => CollectionTrigger::OutOfBlocks,
+            Some(_) => CollectionTrigger::OutOfMemory,
+        };
+        self.start(tracer, trigger);
+        if let Ok(p) = self.try_malloc(num_words) {
+            return Ok(p);
+        }
+        self.finish(tracer);
+        self.try_malloc(num_words)
+    }
+
+    fn assert_no_strays(&self) {
+        // From-space is only vacated once the collection in progress finishes.
+        let spaces = self.spaces.borrow();
+        assert!(spaces.in_progress.is_some() || spaces.heaps[(spaces.active_heap + 1) % 2].next_address == 0);
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, P: TriggerPolicy> EpochCheckedHeap
+    for IncrementalCopyingHeap<HEAP_SIZE, MAX_BLOCKS, P>
+{
+    fn block_epoch(&self, block: usize) -> Option<usize> {
+        self.spaces.borrow().block_info.epoch(block)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, P: TriggerPolicy> PoisonCheckedHeap
+    for IncrementalCopyingHeap<HEAP_SIZE, MAX_BLOCKS, P>
+{
+    fn poison_checked_load(&self, p: Pointer) -> Result<u64, LoadError> {
+        // `load` has left the block in to-space, where it can be checked.
+        self.load(p)?;
+        let spaces = self.spaces.borrow();
+        spaces.heaps[spaces.active_heap].checked_load(spaces.block_info.address(p)?)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, P: TriggerPolicy> CollectableHeap
+    for IncrementalCopyingHeap<HEAP_SIZE, MAX_BLOCKS, P>
+{
+    /// Finishes any collection in progress, then performs a complete one.
+    fn collect<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError> {
+        if self.is_collecting() {
+            self.finish(tracer);
+        }
+        self.start(tracer, CollectionTrigger::Explicit);
+        Ok(self.finish(tracer))
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, P: TriggerPolicy> VerifiableHeap
+    for IncrementalCopyingHeap<HEAP_SIZE, MAX_BLOCKS, P>
+{
+    fn verify(&self) -> Violations {
+        let spaces = self.spaces.borrow();
+        let collections = self.stats.totals().collections + usize::from(spaces.in_progress.is_some());
+        let mut violations = spaces.block_info.verify(collections, |block, _| {
+            let heap = spaces.heap_of(block);
+            (heap, spaces.heaps[heap].next_address)
+        });
+        let inactive = &spaces.heaps[(spaces.active_heap + 1) % 2];
+        if spaces.in_progress.is_none() && inactive.next_address != 0 {
+            violations.push(Violation::Strays(inactive.next_address));
+        }
+        violations
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct MarkSweepHeap<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> {
+    heap: FixedRamHeap<HEAP_SIZE>,
+    block_info: FixedBlockTable<MAX_BLOCKS>,
+    compactions: usize,
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> MarkSweepHeap<HEAP_SIZE, MAX_BLOCKS> {
+    pub fn set_fit_policy(&mut self, fit_policy: FitPolicy) {
+        self.heap.fit_policy = fit_policy;
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> GarbageCollectingHeap
+    for MarkSweepHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn new() -> Self {
+        Self {
+            heap: RamHeap::new(),
+            block_info: BlockTable::new(),
+            compactions: 0,
+        }
+    }
+
+    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        self.block_info.address(p)
+    }
+
+    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heap.load(address))
+    }
+
+    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heap.store(address, value))
+    }
+
+    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
+        self.block_info.blocks_in_use()
+    }
+
+    fn num_allocated_blocks(&self) -> usize {
+        self.block_info.num_allocated_blocks()
+    }
+
+    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        self.block_info.allocated_block_ptr(block)
+    }
+
+    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
+        self.block_info.blocks_num_copies()
+    }
+
+    fn malloc<T: Tracer>(
+        &mut self,
+        num_words: usize,
+        tracer: &T,
+    ) -> Result<Pointer, HeapError> {
+        if num_words == 0 {
+            return Err(HeapError::ZeroSizeRequest);
+        }
+        let block = match self.block_info.available_block() {
+            Some(block) => block,
+            None => {
+                let _ = self.collect(tracer);
+                match self.block_info.available_block() {
+                    Some(block) => block,
+                    None => return Err(HeapError::OutOfBlocks),
+                }
+            }
+        };
+        let start = match self.heap.malloc(num_words) {
+            Ok(start) => start,
+            Err(_) => {
+                let _ = self.collect(tracer);
+                match self.heap.malloc(num_words) {
+                    Ok(start) => start,
+                    Err(_) => {
+                        // The holes may add up to enough words without any one of
+                        // them being big enough, so slide the survivors together.
+                        self.block_info.compact(&mut self.heap);
+                        self.compactions += 1;
+                        self.heap.malloc(num_words)?
+                    }
+                }
+            }
+        };
+
+        self.block_info.set(block, Some(BlockInfo { start, size: num_words, num_times_copied: 0 }));
+        Ok(Pointer::new(block, num_words))
+    }
+
+    fn assert_no_strays(&self) {
+        for (start, size) in self.heap.holes() {
+            assert!(start + size <= self.heap.next_address);
+            for block in self.block_info.blocks_in_use() {
+                let info = self.block_info[block].unwrap();
+                assert!(start + size <= info.start || info.start + info.size <= start);
+            }
+        }
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> EpochCheckedHeap
+    for MarkSweepHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn block_epoch(&self, block: usize) -> Option<usize> {
+        self.block_info.epoch(block)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> PoisonCheckedHeap
+    for MarkSweepHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn poison_checked_load(&self, p: Pointer) -> Result<u64, LoadError> {
+        self.heap.checked_load(self.block_info.address(p)?)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> CollectableHeap
+    for MarkSweepHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    /// Sweeps every block that `tracer` does not report as live.
+    fn collect<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError> {
+        let before = self.block_info.usage();
+        let mut blocks = [false; MAX_BLOCKS];
+        tracer.trace(&mut blocks);
+
+        for (i, &marked) in blocks.iter().enumerate() {
+            if !marked {
+                if let Some(info) = self.block_info[i] {
+                    self.heap.free(info.start, info.size);
+                    self.block_info.set(i, None);
+                }
+            }
+        }
+        Ok(CollectionSummary::new(before, self.block_info.usage()))
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> VerifiableHeap
+    for MarkSweepHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn verify(&self) -> Violations {
+        // Blocks are only copied when fragmentation forces a compaction.
+        let mut violations =
+            self.block_info.verify(self.compactions, |_, _| (0, self.heap.next_address));
+        for (start, size) in self.heap.holes() {
+            self.block_info.verify_free(start, size, &mut violations);
+        }
+        violations
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct MarkCompactHeap<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> {
+    heap: FixedRamHeap<HEAP_SIZE>,
+    block_info: FixedBlockTable<MAX_BLOCKS>,
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> GarbageCollectingHeap
+    for MarkCompactHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn new() -> Self {
+        Self {
+            heap: RamHeap::new(),
+            block_info: BlockTable::new(),
+        }
+    }
+
+    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        self.block_info.address(p)
+    }
+
+    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heap.load(address))
+    }
+
+    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heap.store(address, value))
+    }
+
+    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
+        self.block_info.blocks_in_use()
+    }
+
+    fn num_allocated_blocks(&self) -> usize {
+        self.block_info.num_allocated_blocks()
+    }
+
+    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        self.block_info.allocated_block_ptr(block)
+    }
+
+    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
+        self.block_info.blocks_num_copies()
+    }
+
+    fn malloc<T: Tracer>(
+        &mut self,
+        num_words: usize,
+        tracer: &T,
+    ) -> Result<Pointer, HeapError> {
+        if num_words == 0 {
+            return Err(HeapError::ZeroSizeRequest);
+        }
+        let block = match self.block_info.available_block() {
+            Some(block) => block,
+            None => {
+                let _ = self.collect(tracer);
+                match self.block_info.available_block() {
+                    Some(block) => block,
+                    None => return Err(HeapError::OutOfBlocks),
+                }
+            }
+        };
+        let start = match self.heap.malloc(num_words) {
+            Ok(start) => start,
+            Err(_) => {
+                let _ = self.collect(tracer);
+                self.heap.malloc(num_words)?
+            }
+        };
+
+        self.block_info.set(block, Some(BlockInfo { start, size: num_words, num_times_copied: 0 }));
+        Ok(Pointer::new(block, num_words))
+    }
+
+    fn assert_no_strays(&self) {
+        let words_in_use: usize = self
+            .block_info
+            .blocks_in_use()
+            .map(|b| self.block_info[b].unwrap().size)
+            .sum();
+        assert_eq!(words_in_use, self.heap.next_address);
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> EpochCheckedHeap
+    for MarkCompactHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn block_epoch(&self, block: usize) -> Option<usize> {
+        self.block_info.epoch(block)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> PoisonCheckedHeap
+    for MarkCompactHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn poison_checked_load(&self, p: Pointer) -> Result<u64, LoadError> {
+        self.heap.checked_load(self.block_info.address(p)?)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> CollectableHeap
+    for MarkCompactHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    /// Frees every block that `tracer` does not report as live and slides the survivors to address 0.
+    fn collect<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError> {
+        let before = self.block_info.usage();
+        let mut blocks = [false; MAX_BLOCKS];
+        tracer.trace(&mut blocks);
+
+        for (i, &marked) in blocks.iter().enumerate() {
+            if !marked {
+                self.block_info.set(i, None);
+            }
+        }
+        self.block_info.compact(&mut self.heap);
+        Ok(CollectionSummary::new(before, self.block_info.usage()))
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> VerifiableHeap
+    for MarkCompactHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn verify(&self) -> Violations {
+        // Sliding increments `num_times_copied`, but nothing bounds how many collections there were.
+        self.block_info.verify(usize::MAX, |_, _| (0, self.heap.next_address))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SegregatedHeap<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> {
+    heap: SizeClassRamHeap<HEAP_SIZE>,
+    block_info: FixedBlockTable<MAX_BLOCKS>,
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> GarbageCollectingHeap
+    for SegregatedHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn new() -> Self {
+        Self {
+            heap: SizeClassRamHeap::new(),
+            block_info: BlockTable::new(),
+        }
+    }
+
+    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        self.block_info.address(p)
+    }
+
+    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heap.load(address))
+    }
+
+    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heap.store(address, value))
+    }
+
+    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
+        self.block_info.blocks_in_use()
+    }
+
+    fn num_allocated_blocks(&self) -> usize {
+        self.block_info.num_allocated_blocks()
+    }
+
+    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        self.block_info.allocated_block_ptr(block)
+    }
+
+    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
+        self.block_info.blocks_num_copies()
+    }
+
+    fn malloc<T: Tracer>(
+        &mut self,
+        num_words: usize,
+        tracer: &T,
+    ) -> Result<Pointer, HeapError> {
+        if num_words == 0 {
+            return Err(HeapError::ZeroSizeRequest);
+        }
+        let block = match self.block_info.available_block() {
+            Some(block) => block,
+            None => {
+                let _ = self.collect(tracer);
+                match self.block_info.available_block() {
+                    Some(block) => block,
+                    None => return Err(HeapError::OutOfBlocks),
+                }
+            }
+        };
+        let start = match self.heap.malloc(num_words) {
+            Ok(start) => start,
+            Err(_) => {
+                let _ = self.collect(tracer);
+                self.heap.malloc(num_words)?
+            }
+        };
+
+        self.block_info.set(block, Some(BlockInfo { start, size: num_words, num_times_copied: 0 }));
+        Ok(Pointer::new(block, num_words))
+    }
+
+    fn assert_no_strays(&self) {
+        let small = (1..=NUM_SIZE_CLASSES)
+            .flat_map(|size| self.heap.size_class(size).map(move |start| (start, size)));
+        for (start, size) in self.heap.region.holes().chain(small) {
+            assert!(start + size <= self.heap.region.next_address);
+            for block in self.block_info.blocks_in_use() {
+                let info = self.block_info[block].unwrap();
+                assert!(start + size <= info.start || info.start + info.size <= start);
+            }
+        }
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> EpochCheckedHeap
+    for SegregatedHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn block_epoch(&self, block: usize) -> Option<usize> {
+        self.block_info.epoch(block)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> PoisonCheckedHeap
+    for SegregatedHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn poison_checked_load(&self, p: Pointer) -> Result<u64, LoadError> {
+        self.heap.checked_load(self.block_info.address(p)?)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> CollectableHeap
+    for SegregatedHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    /// Sweeps every block that `tracer` does not report as live.
+    fn collect<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError> {
+        let before = self.block_info.usage();
+        let mut blocks = [false; MAX_BLOCKS];
+        tracer.trace(&mut blocks);
+
+        for (i, &marked) in blocks.iter().enumerate() {
+            if !marked {
+                if let Some(info) = self.block_info[i] {
+                    self.heap.free(info.start, info.size);
+                    self.block_info.set(i, None);
+                }
+            }
+        }
+        Ok(CollectionSummary::new(before, self.block_info.usage()))
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> VerifiableHeap
+    for SegregatedHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn verify(&self) -> Violations {
+        let mut violations = self.block_info.verify(0, |_, _| (0, self.heap.region.next_address));
+        let small = (1..=NUM_SIZE_CLASSES)
+            .flat_map(|size| self.heap.size_class(size).map(move |start| (start, size)));
+        for (start, size) in self.heap.region.holes().chain(small) {
+            self.block_info.verify_free(start, size, &mut violations);
+        }
+        violations
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct BuddyHeap<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> {
+    heap: BuddyRamHeap<HEAP_SIZE>,
+    block_info: FixedBlockTable<MAX_BLOCKS>,
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> BuddyHeap<HEAP_SIZE, MAX_BLOCKS> {
+    /// Words lost to rounding live blocks up to a power of two.
+    pub fn internal_fragmentation(&self) -> usize {
+        self.heap.internal_fragmentation()
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> GarbageCollectingHeap
+    for BuddyHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn new() -> Self {
+        Self {
+            heap: BuddyRamHeap::new(),
+            block_info: BlockTable::new(),
+        }
+    }
+
+    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        self.block_info.address(p)
+    }
+
+    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heap.load(address))
+    }
+
+    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heap.store(address, value))
+    }
+
+    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
+        self.block_info.blocks_in_use()
+    }
+
+    fn num_allocated_blocks(&self) -> usize {
+        self.block_info.num_allocated_blocks()
+    }
+
+    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        self.block_info.allocated_block_ptr(block)
+    }
+
+    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
+        self.block_info.blocks_num_copies()
+    }
+
+    fn malloc<T: Tracer>(
+        &mut self,
+        num_words: usize,
+        tracer: &T,
+    ) -> Result<Pointer, HeapError> {
+        if num_words == 0 {
+            return Err(HeapError::ZeroSizeRequest);
+        }
+        let block = match self.block_info.available_block() {
+            Some(block) => block,
+            None => {
+                let _ = self.collect(tracer);
+                match self.block_info.available_block() {
+                    Some(block) => block,
+                    None => return Err(HeapError::OutOfBlocks),
+                }
+            }
+        };
+        let start = match self.heap.malloc(num_words) {
+            Ok(start) => start,
+            Err(_) => {
+                let _ = self.collect(tracer);
+                self.heap.malloc(num_words)?
+            }
+        };
+
+        self.block_info.set(block, Some(BlockInfo { start, size: num_words, num_times_copied: 0 }));
+        Ok(Pointer::new(block, num_words))
+    }
+
+    fn assert_no_strays(&self) {
+        let words_allocated: usize = self
+            .block_info
+            .blocks_in_use()
+            .map(|b| self.block_info[b].unwrap().size.next_power_of_two())
+            .sum();
+        assert_eq!(words_allocated, self.heap.words_allocated);
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> EpochCheckedHeap
+    for BuddyHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn block_epoch(&self, block: usize) -> Option<usize> {
+        self.block_info.epoch(block)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> PoisonCheckedHeap
+    for BuddyHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn poison_checked_load(&self, p: Pointer) -> Result<u64, LoadError> {
+        self.heap.checked_load(self.block_info.address(p)?)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> CollectableHeap
+    for BuddyHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    /// Sweeps every block that `tracer` does not report as live.
+    fn collect<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError> {
+        let before = self.block_info.usage();
+        let mut blocks = [false; MAX_BLOCKS];
+        tracer.trace(&mut blocks);
+
+        for (i, &marked) in blocks.iter().enumerate() {
+            if !marked {
+                if let Some(info) = self.block_info[i] {
+                    self.heap.free(info.start, info.size);
+                    self.block_info.set(i, None);
+                }
+            }
+        }
+        Ok(CollectionSummary::new(before, self.block_info.usage()))
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize> VerifiableHeap
+    for BuddyHeap<HEAP_SIZE, MAX_BLOCKS>
+{
+    fn verify(&self) -> Violations {
+        let mut violations = self.block_info.verify(0, |_, _| (0, HEAP_SIZE));
+        for (start, size) in self.heap.free_chunks() {
+            self.block_info.verify_free(start, size, &mut violations);
+        }
+        violations
+    }
+}
+
+/// The share of generation 0 that survivors may fill before adaptive tenuring promotes them sooner.
+const TARGET_SURVIVOR_PERCENT: usize = 50;
+
+// The active and inactive heaps of a generation.
+type Semispaces<'a, W> = (&'a mut RamHeap<W>, &'a mut RamHeap<W>);
+
+/// What `GenerationalHeap` keeps in the block table for each block. Its fields are private.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct GenerationalSlot {
+    // Whether the block is in generation 1, whether it may refer to generation 0, and
+    // whether the current collection promoted it.
+    tenured: bool,
+    remembered: bool,
+    promoted: bool,
+    next_scan: usize,
+}
+
+impl ScanLink for GenerationalSlot {
+    fn next_scan(&mut self) -> &mut usize {
+        &mut self.next_scan
+    }
+}
+
+/// A two-generation copying collector. Generation 0 is kept in `W0`, generation 1 in `W1`,
+/// its block table in `B`, and in `M` the marks that a `Tracer` fills, one per block; see
+/// `GenerationalHeap`, `SliceGenerationalHeap` and `VecGenerationalHeap`.
+#[derive(Copy, Clone, Debug)]
+pub struct GenerationalHeapIn<
+    W0,
+    W1,
+    B,
+    M,
+    const MAX_COPIES: usize,
+    O: HeapObserver = NoObserver,
+    P: TriggerPolicy = CollectOnFailure,
+    const CARD_SIZE: usize = 0,
+    const CARDS: usize = 0,
+> {
+    gen_0: [RamHeap<W0>; 2],
+    gen_1: [RamHeap<W1>; 2],
+    block_info: BlockTable<B, GenerationalSlot>,
+    marks: M,
+    active_gen_0: usize,
+    active_gen_1: usize,
+    tenuring_threshold: usize,
+    // The lowest threshold used so far: every promoted block was copied more times than this.
+    lowest_threshold: usize,
+    adaptive_tenuring: bool,
+    // With card marking, `cards[i]` is nonzero when words `i * CARD_SIZE..(i + 1) * CARD_SIZE`
+    // of the active generation 1 heap may refer to generation 0.
+    cards: [u8; CARDS],
+    stress_mode: StressMode,
+    stats: GcStats,
+    observer: O,
+    trigger_policy: P,
+    growth: Option<GrowthPolicy>,
+}
+
+/// New blocks are allocated in a pair of `NURSERY_SIZE`-word semispaces. A block that has
+/// been copied `tenuring_threshold()` times is promoted by the next collection to generation
+/// 1, a pair of `OLD_SIZE`-word semispaces that only full collections trace. `OLD_SIZE`
+/// comes last so that it can default to `NURSERY_SIZE`. The threshold is
+/// `MAX_COPIES` unless adaptive tenuring lowers it. References from generation 1 into
+/// generation 0 are found by a write barrier in `store`: with `CARD_SIZE` zero it records the
+/// blocks written in a remembered set, and otherwise it marks dirty the `CARD_SIZE`-word card
+/// written. The card table has one byte per card, so `CARDS` must be
+/// `OLD_SIZE.div_ceil(CARD_SIZE)`, or zero without card marking.
+pub type GenerationalHeap<
+    const NURSERY_SIZE: usize,
+    const MAX_BLOCKS: usize,
+    const MAX_COPIES: usize,
+    O = NoObserver,
+    P = CollectOnFailure,
+    const CARD_SIZE: usize = 0,
+    const CARDS: usize = 0,
+    const OLD_SIZE: usize = NURSERY_SIZE,
+> = GenerationalHeapIn<
+    [u64; NURSERY_SIZE],
+    [u64; OLD_SIZE],
+    [BlockSlot<GenerationalSlot>; MAX_BLOCKS],
+    [bool; MAX_BLOCKS],
+    MAX_COPIES,
+    O,
+    P,
+    CARD_SIZE,
+    CARDS,
+>;
+
+impl<
+        W0: Buffer<u64>,
+        W1: Buffer<u64>,
+        B: Buffer<BlockSlot<GenerationalSlot>>,
+        M: Buffer<bool>,
+        const MAX_COPIES: usize,
+        O: HeapObserver,
+        P: TriggerPolicy,
+        const CARD_SIZE: usize,
+        const CARDS: usize,
+    >
+    GenerationalHeapIn<W0, W1, B, M, MAX_COPIES, O, P, CARD_SIZE, CARDS>
+{
+    fn in_storage(gen_0: [W0; 2], gen_1: [W1; 2], blocks: B, marks: M) -> Self {
+        assert_eq!(blocks.as_ref().len(), marks.as_ref().len());
+        let [gen_0_a, gen_0_b] = gen_0;
+        let [gen_1_a, gen_1_b] = gen_1;
+        let old_size = gen_1_a.as_ref().len();
+        assert!(CARDS == if CARD_SIZE == 0 { 0 } else { old_size.div_ceil(CARD_SIZE) });
+        Self {
+            gen_0: [RamHeap::in_words(gen_0_a), RamHeap::in_words(gen_0_b)],
+            gen_1: [RamHeap::in_words(gen_1_a), RamHeap::in_words(gen_1_b)],
+            block_info: BlockTable::in_slots(blocks),
+            marks,
+            active_gen_0: 0,
+            active_gen_1: 0,
+            tenuring_threshold: MAX_COPIES,
+            lowest_threshold: MAX_COPIES,
+            adaptive_tenuring: false,
+            cards: [0; CARDS],
+            stress_mode: StressMode::Off,
+            stats: GcStats::default(),
+            observer: O::default(),
+            trigger_policy: P::default(),
+            growth: None,
+        }
+    }
+
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    pub fn set_stress_mode(&mut self, stress_mode: StressMode) {
+        self.stress_mode = stress_mode;
+    }
+
+    pub fn trigger_policy(&self) -> &P {
+        &self.trigger_policy
+    }
+
+    pub fn set_trigger_policy(&mut self, trigger_policy: P) {
+        self.trigger_policy = trigger_policy;
+    }
+
+    /// With adaptive tenuring, each minor collection picks a threshold between 1 and
+    /// `MAX_COPIES` from how full generation 0 is left. Turning it off restores `MAX_COPIES`.
+    pub fn set_adaptive_tenuring(&mut self, adaptive: bool) {
+        self.adaptive_tenuring = adaptive;
+        if !adaptive {
+            self.tenuring_threshold = MAX_COPIES;
+        }
+    }
+
+    /// How many times a block is copied within generation 0 before being promoted.
+    pub fn tenuring_threshold(&self) -> usize {
+        self.tenuring_threshold
+    }
+
+    /// Generation 1 only grows without card marking, as the card table has a fixed size.
+    pub fn set_growth_policy(&mut self, growth: Option<GrowthPolicy>) {
+        self.growth = growth;
+    }
+
+    /// Picks the youngest age at which the survivors of that age or younger fill more than
+    /// `TARGET_SURVIVOR_PERCENT` of generation 0, so that the older ones are promoted next
+    /// time and make room. When the survivors fit, the threshold returns to `MAX_COPIES`.
+    fn adapt_tenuring_threshold(&mut self) {
+        let target = self.gen_0[self.active_gen_0].size() * TARGET_SURVIVOR_PERCENT / 100;
+        let words_up_to = |age: usize| -> usize {
+            self.blocks_num_copies()
+                .filter(|(block, copies)| !self.block_info.collector(*block).tenured && *copies <= age)
+                .map(|(block, _)| self.block_info[block].unwrap().size)
+                .sum()
+        };
+        self.tenuring_threshold = (1..MAX_COPIES).find(|age| words_up_to(*age) > target).unwrap_or(MAX_COPIES);
+        self.lowest_threshold = self.lowest_threshold.min(self.tenuring_threshold);
+    }
+
+    fn occupancy(&self) -> HeapOccupancy {
+        HeapOccupancy {
+            blocks_in_use: self.block_info.num_allocated_blocks(),
+            max_blocks: self.block_info.len(),
+            words_in_use: self.gen_0[self.active_gen_0].next_address,
+            heap_size: self.gen_0[self.active_gen_0].size(),
+        }
+    }
+
+    fn active_inactive_gen_0_gen_1(
+        &mut self,
+    ) -> (Semispaces<'_, W0>, Semispaces<'_, W1>, &mut BlockTable<B, GenerationalSlot>, &mut O) {
+        let inactive_0 = (self.active_gen_0 + 1) % 2;
+        let inactive_1 = (self.active_gen_1 + 1) % 2;
+        let (active_0, inactive_0) =
+            independent_elements_from(self.active_gen_0, inactive_0, &mut self.gen_0).unwrap();
+        let (active_1, inactive_1) =
+            independent_elements_from(self.active_gen_1, inactive_1, &mut self.gen_1).unwrap();
+        (
+            (active_0, inactive_0),
+            (active_1, inactive_1),
+            &mut self.block_info,
+            &mut self.observer,
+        )
+    }
+
+    fn heap_and_gen_for(&self, block_num: usize) -> Result<(usize, usize), HeapError> {
+        if block_num >= self.block_info.len() {
+            Err(HeapError::IllegalBlock(block_num, self.block_info.len() - 1))
+        } else {
+            match self.block_info[block_num] {
+                Some(_) => Ok(if self.block_info.collector(block_num).tenured {
+                    (self.active_gen_1, 1)
+                } else {
+                    (self.active_gen_0, 0)
+                }),
+                None => Err(HeapError::UnallocatedBlock(block_num)),
+            }
+        }
+    }
+
+    fn in_gen_1(&self, block: usize) -> bool {
+        self.block_info[block].is_some() && self.block_info.collector(block).tenured
+    }
+
+    /// The generation 0 block that `word` refers to, if any.
+    fn gen_0_referent(block_info: &BlockTable<B, GenerationalSlot>, word: u64) -> Option<usize> {
+        decode_pointer(word)
+            .filter(|p| block_info.address(*p).is_ok() && !block_info.collector(p.block_num()).tenured)
+            .map(|p| p.block_num())
+    }
+
+    /// Whether generation 1 `block` holds a reference to a block in generation 0.
+    fn refers_to_gen_0(&self, block: usize) -> bool {
+        let info = self.block_info[block].unwrap();
+        let words = &self.gen_1[self.active_gen_1].heap.as_ref()[info.start..info.start + info.size];
+        words.iter().any(|word| Self::gen_0_referent(&self.block_info, *word).is_some())
+    }
+
+    /// Marks dirty the cards covering `start..end` of the active generation 1 heap that
+    /// hold references to generation 0.
+    fn mark_cards(&self, cards: &mut [u8; CARDS], start: usize, end: usize) {
+        let gen_1 = &self.gen_1[self.active_gen_1];
+        for address in start..end.min(gen_1.next_address) {
+            if Self::gen_0_referent(&self.block_info, gen_1.heap.as_ref()[address]).is_some() {
+                cards[address / CARD_SIZE] = 1;
+            }
+        }
+    }
+
+    /// Marks in `marks` the blocks reachable from `tracer`, returning the number of cards
+    /// scanned. A minor trace starts from the remembered set or the dirty cards as well as
+    /// the roots and never looks inside generation 1, every block of which it marks.
+    fn trace<T: Tracer>(&mut self, tracer: &T, minor: bool) -> Result<usize, HeapError> {
+        let used_blocks = self.marks.as_mut();
+        let mut cards_scanned = 0;
+        used_blocks.fill(false);
+        tracer.trace(used_blocks);
+        if minor && CARD_SIZE == 0 {
+            for (block, used) in used_blocks.iter_mut().enumerate() {
+                *used |= self.block_info.collector(block).remembered;
+            }
+        } else if minor {
+            let gen_1 = &self.gen_1[self.active_gen_1];
+            for (card, _) in self.cards.iter().enumerate().filter(|(_, dirty)| **dirty != 0) {
+                cards_scanned += 1;
+                for address in card * CARD_SIZE..((card + 1) * CARD_SIZE).min(gen_1.next_address) {
+                    if let Some(block) = Self::gen_0_referent(&self.block_info, gen_1.load(address)?) {
+                        used_blocks[block] = true;
+                    }
+                }
+            }
+        }
+        let (gen_0, gen_1) = (&self.gen_0[self.active_gen_0], &self.gen_1[self.active_gen_1]);
+        self.block_info.trace_interior(
+            used_blocks,
+            |_, slot| !minor || !slot.tenured || slot.remembered,
+            |_, slot, address| if slot.tenured { gen_1.load(address) } else { gen_0.load(address) },
+        )?;
+        if minor {
+            for block in self.block_info.blocks_in_use() {
+                used_blocks[block] |= self.block_info.collector(block).tenured;
+            }
+        }
+        Ok(cards_scanned)
+    }
+
+    /// Rebuilds the remembered set or card table after a collection. Promoted blocks may
+    /// refer to blocks left behind in generation 0, while remembered blocks and dirty cards
+    /// may no longer refer to any. Collecting generation 1 moves or frees all of it.
+    /// It runs once the collection has committed, so it only reads words of live blocks,
+    /// which lie below `next_address`, and cannot fail.
+    fn refresh_barrier(&mut self, gen_1_collected: bool) {
+        if CARD_SIZE == 0 {
+            for block in 0..self.block_info.len() {
+                let slot = *self.block_info.collector(block);
+                let candidate = slot.promoted || slot.remembered || (gen_1_collected && slot.tenured);
+                self.block_info.collector_mut(block).remembered =
+                    candidate && self.block_info[block].is_some() && self.refers_to_gen_0(block);
+            }
+        } else {
+            let mut cards = [0; CARDS];
+            if gen_1_collected {
+                self.mark_cards(&mut cards, 0, self.gen_1[self.active_gen_1].size());
+            } else {
+                for (card, _) in self.cards.iter().enumerate().filter(|(_, dirty)| **dirty != 0) {
+                    self.mark_cards(&mut cards, card * CARD_SIZE, (card + 1) * CARD_SIZE);
+                }
+                for block in (0..self.block_info.len()).filter(|b| self.block_info.collector(*b).promoted) {
+                    let info = self.block_info[block].unwrap();
+                    self.mark_cards(&mut cards, info.start, info.start + info.size);
+                }
+            }
+            self.cards = cards;
+        }
+        for block in 0..self.block_info.len() {
+            self.block_info.collector_mut(block).promoted = false;
+        }
+    }
+
+    pub fn stats(&self) -> &GcStats {
+        &self.stats
+    }
+
+    /// Grows both generation 1 heaps to hold `needed` words, if the growth policy allows.
+    /// The card table has a fixed size, so a heap with card marking never grows generation 1.
+    fn grow_gen_1(&mut self, needed: usize) -> bool {
+        match self.growth {
+            Some(policy) if CARD_SIZE == 0 => policy.grow_words(&mut self.gen_1, needed),
+            _ => false,
+        }
+    }
+
+    /// Adds blocks to the table if the growth policy allows, returning the first one added.
+    fn grow_blocks(&mut self) -> Option<usize> {
+        let policy = self.growth?;
+        if policy.grow_blocks(&mut self.block_info, &mut self.marks) {
+            self.block_info.available_block()
+        } else {
+            None
+        }
+    }
+
+    /// Allocates `num_words` after growing both generation 0 heaps, if the growth policy allows.
+    fn grow_and_malloc(&mut self, num_words: usize) -> Result<usize, HeapError> {
+        let needed = self.gen_0[self.active_gen_0].next_address + num_words;
+        match self.growth {
+            Some(policy) if policy.grow_words(&mut self.gen_0, needed) => {
+                self.gen_0[self.active_gen_0].malloc(num_words)
+            }
+            _ => Err(HeapError::OutOfMemory),
+        }
+    }
+
+    fn collect_gen_0<T: Tracer>(
+        &mut self,
+        tracer: &T,
+        full: bool,
+        trigger: CollectionTrigger,
+    ) -> Result<CollectionSummary, HeapError> {
+        let before = self.block_info.usage();
+        let mut cards_scanned = self.trace(tracer, !full)?;
+
+        // Generation 0 survivors always fit in the other generation 0 heap, so only promotions
+        // can run out of room. Deciding up front whether generation 1 must be collected, and
+        // whether even that would leave enough room, means a collection that cannot succeed
+        // fails before anything has been freed or moved. Collecting generation 1 needs to know
+        // which of its blocks are live, so a minor trace is then redone as a full one.
+        let mut gen_1_collected = full;
+        let (promoted_words, gen_1_words) = loop {
+            let mut promoted_words = 0;
+            let mut gen_1_words = 0;
+            for block in self.block_info.blocks_in_use().filter(|b| self.marks.as_ref()[*b]) {
+                let info = self.block_info[block].unwrap();
+                if self.block_info.collector(block).tenured {
+                    gen_1_words += info.size;
+                } else if info.num_times_copied >= self.tenuring_threshold {
+                    promoted_words += info.size;
+                }
+            }
+            let gen_1 = &self.gen_1[self.active_gen_1];
+            if gen_1_collected || gen_1.next_address + promoted_words <= gen_1.size() {
+                break (promoted_words, gen_1_words);
+            }
+            gen_1_collected = true;
+            cards_scanned = self.trace(tracer, false)?;
+        };
+        let needed = gen_1_words + promoted_words;
+        if gen_1_collected && needed > self.gen_1[self.active_gen_1].size() && !self.grow_gen_1(needed) {
+            return Err(HeapError::OutOfMemory);
+        }
+        self.observer.on_collect_start();
+
+        // Freeing the blocks the trace did not reach first leaves only survivors in the table.
+        for block in 0..self.block_info.len(){
+            if !self.marks.as_ref()[block] && self.block_info[block].is_some(){
+                self.observer.on_block_freed(block, self.block_info[block].unwrap().size);
+                self.block_info.set(block, None);
+            }
+        }
+
+        let threshold = self.tenuring_threshold;
+        let ((active_0, inactive_0), (active_1, inactive_1), block_info, observer) =
+            self.active_inactive_gen_0_gen_1();
+        //todo!("Complete implementation.");
+
+        // Every generation 0 survivor is copied, and those at the threshold are promoted.
+        let mut words_copied = 0;
+        let mut promotions = 0;
+        for block in block_info.blocks_in_use().filter(|b| !block_info.collector(*b).tenured) {
+            let info = block_info[block].unwrap();
+            words_copied += info.size;
+            if info.num_times_copied >= threshold {
+                promotions += 1;
+            }
+        }
+
+        let promote_to = if gen_1_collected {
+            words_copied += Self::collect_gen_1(block_info, active_1, inactive_1, observer);
+            inactive_1
+        } else {
+            active_1
+        };
+
+        for block in 0..block_info.len(){
+            if let Some(used_info) = block_info[block].filter(|_| !block_info.collector(block).tenured) {
+                if used_info.num_times_copied >= threshold {
+                    let new_info = active_0.copy(&used_info, promote_to).expect("promotions fit");
+                    block_info.set(block, Some(new_info));
+                    let slot = block_info.collector_mut(block);
+                    slot.tenured = true;
+                    slot.promoted = true;
+                    observer.on_promote(block, used_info.size);
+                } else {
+                    let new_info = active_0.copy(&used_info, inactive_0).expect("survivors fit");
+                    block_info.set(block, Some(new_info));
+                    observer.on_block_copied(block, used_info.size);
+                }
+            }
+        }
+        active_0.clear();
+        self.active_gen_0 = (self.active_gen_0 + 1) % 2;
+        if gen_1_collected{
+            self.active_gen_1 = (self.active_gen_1 + 1) % 2;
+        }
+        if self.stress_mode == StressMode::CollectAndPoison {
+            self.gen_0[(self.active_gen_0 + 1) % 2].poison();
+            if gen_1_collected {
+                self.gen_1[(self.active_gen_1 + 1) % 2].poison();
+            }
+        }
+
+        self.refresh_barrier(gen_1_collected);
+
+        let summary = CollectionSummary::new(before, self.block_info.usage());
+        self.stats.record(CollectionRecord {
+            trigger,
+            blocks_live: summary.blocks_live,
+            words_copied,
+            words_reclaimed: summary.words_reclaimed,
+            promotions,
+            gen_1_collected,
+            cards_scanned,
+        });
+        if self.adaptive_tenuring && !full {
+            self.adapt_tenuring_threshold();
+        }
+        self.trigger_policy.on_collect();
+        self.observer.on_collect_end(summary);
+        Ok(summary)
+        // Outline
+        //
+        // 1. Call the tracer to find out what blocks are in use.
+        // 2. For each block in use:
+        //    * If it has been copied MAX_COPIES times
+        //      * You'll need a variable to track whether you have already performed a generation 1 collection.
+        //      * If so, just return the error - multiple generation 1 collections will not be productive
+        //      * If not, copy into the active generation 1 heap.
+        //      * If that heap is out of space, perform a generation 1 collection by calling self.collect_gen_1().
+        //      * After the generation 1 collection, try copying it into the inactive generation 1 heap.
+        //    * If not, copy it into the inactive generation 0 heap.
+        // 3. Clear the active generation 0 heap.
+        // 4. Update self.active_gen_0 to the other heap.
+        // 5. If there was a generation 1 collection, update self.active_gen_1 to the other heap.
+    }
+
+    /// Copies every generation 1 block left in `block_info`, which by now holds only survivors.
+    fn collect_gen_1(
+        block_info: &mut BlockTable<B, GenerationalSlot>,
+        src: &mut RamHeap<W1>,
+        dest: &mut RamHeap<W1>,
+        observer: &mut O,
+    ) -> usize {
+        //todo!("Complete implementation.");
+        let mut words_copied = 0;
+        for block in 0..block_info.len(){
+            if let Some(used_info) = block_info[block] {
+               if block_info.collector(block).tenured {
+                    let new_info: BlockInfo = src.copy(&used_info, dest).expect("generation 1 fits");
+                    block_info.set(block, Some(new_info));
+                    words_copied += used_info.size;
+                    observer.on_block_copied(block, used_info.size);
+                } 
+            }
+        }
+        src.clear();
+        words_copied
+        // Outline
+        //
+        // 1. For each block in use:
+        //    * If it has been promoted to generation 1, copy it to `dest`
+        // 2. Clear the `src` heap.
+    }
+
+    pub fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        let (heap, gen) = self.heap_and_gen_for(p.block_num())?;
+        let address = self.block_info.address(p)?;
+        if gen == 0 {
+            self.gen_0[heap].load(address)
+        } else {
+            self.gen_1[heap].load(address)
+        }
+    }
+
+    pub fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        let (heap, gen) = self.heap_and_gen_for(p.block_num())?;
+        let address = self.block_info.address(p)?;
+        // The write barrier: a generation 1 block that now refers to generation 0 is remembered,
+        // or with card marking the card holding the word written is marked dirty.
+        if gen == 1 && Self::gen_0_referent(&self.block_info, value).is_some() {
+            match CARD_SIZE {
+                0 => self.block_info.collector_mut(p.block_num()).remembered = true,
+                card_size => self.cards[address / card_size] = 1,
+            }
+        }
+        if gen == 0 {
+            self.gen_0[heap].store(address, value)
+        } else {
+            self.gen_1[heap].store(address, value)
+        }
+    }
+
+    pub fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        self.block_info.address(p)
+    }
+
+    pub fn blocks_in_use(&self) -> impl Iterator<Item = usize> + '_ {
+        self.block_info.blocks_in_use()
+    }
+
+    pub fn num_allocated_blocks(&self) -> usize {
+        self.block_info.num_allocated_blocks()
+    }
+
+    pub fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        self.block_info.allocated_block_ptr(block)
+    }
+
+    pub fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.block_info.blocks_num_copies()
+    }
+
+    /// When a collection does not free enough, a heap with a growth policy grows instead
+    /// of failing.
+    pub fn malloc<T: Tracer>(
+        &mut self,
+        num_words: usize,
+        tracer: &T,
+    ) -> Result<Pointer, HeapError> {
+        //todo!("Implement generational malloc");
+        if num_words == 0 {
+            return Err(HeapError::ZeroSizeRequest);
+        }
+        if self.stress_mode != StressMode::Off {
+            self.collect_gen_0(tracer, true, CollectionTrigger::Stress)?;
+        } else if self.trigger_policy.should_collect(self.occupancy(), num_words) {
+            // An early collection is only an optimisation, so the request may still
+            // succeed without it.
+            let _ = self.collect_gen_0(tracer, false, CollectionTrigger::Policy);
+        }
+        let block = match self.block_info.available_block(){
+            Some(block) => block,
+            None => {
+                self.collect_gen_0(tracer, false, CollectionTrigger::OutOfBlocks)?;
+                match self.block_info.available_block(){
+                    Some(block) => block,
+                    None => self.grow_blocks().ok_or(HeapError::OutOfBlocks)?
+                }
+            }
+        };
+        let start = match self.gen_0[self.active_gen_0].malloc(num_words){
+            Ok(ad) => ad,
+            Err(_) => {
+                self.collect_gen_0(tracer, false, CollectionTrigger::OutOfMemory)?;
+                match self.gen_0[self.active_gen_0].malloc(num_words){
+                    Ok(ad) => ad,
+                    Err(_) => self.grow_and_malloc(num_words)?
+                }
+            }
+        };
+
+        self.block_info.set(block, Some(BlockInfo{start, size: num_words, num_times_copied: 0}));
+        self.block_info.collector_mut(block).tenured = false;
+        self.observer.on_malloc(block, num_words);
+        self.trigger_policy.on_malloc(num_words);
+        Ok(Pointer::new(block, num_words))
+        // Outline
+        //
+        // 1. Find an available block number
+        //    * If none are available, perform a collection by calling self.collect_gen_0().
+        //    * If none are still available, report out of blocks.
+        // 2. Perform a generation zero malloc.
+        //    * If no space is available, perform a collection by calling self.collect_gen_0().
+        //    * If no space is still available, report out of memory.
+        // 3. Create entry in the block table for the newly allocated block.
+        // 4. Return a pointer to the newly allocated block.
+    }
+
+    pub fn assert_no_strays(&self) {
+        assert!(self.gen_0[(self.active_gen_0 + 1) % 2].next_address == 0);
+        assert!(self.gen_1[(self.active_gen_1 + 1) % 2].next_address == 0);
+    }
+}
+
+// As with `OnceAndDoneHeap`, only the array-backed heap can be built by `new`.
+impl<
+        const NURSERY_SIZE: usize,
+        const MAX_BLOCKS: usize,
+        const MAX_COPIES: usize,
+        O: HeapObserver,
+        P: TriggerPolicy,
+        const CARD_SIZE: usize,
+        const CARDS: usize,
+        const OLD_SIZE: usize,
+    >
+    GarbageCollectingHeap for GenerationalHeap<NURSERY_SIZE, MAX_BLOCKS, MAX_COPIES, O, P, CARD_SIZE, CARDS, OLD_SIZE>
+{
+    fn new() -> Self {
+        const {
+            assert!(CARDS == if CARD_SIZE == 0 { 0 } else { OLD_SIZE.div_ceil(CARD_SIZE) });
+        }
+        Self::in_storage(
+            [[0; NURSERY_SIZE]; 2],
+            [[0; OLD_SIZE]; 2],
+            [BlockSlot::default(); MAX_BLOCKS],
+            [false; MAX_BLOCKS],
+        )
+    }
+
+    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        GenerationalHeapIn::load(self, p)
+    }
+
+    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        GenerationalHeapIn::store(self, p, value)
+    }
+
+    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        GenerationalHeapIn::address(self, p)
+    }
+
+    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
+        GenerationalHeapIn::blocks_in_use(self)
+    }
+
+    fn num_allocated_blocks(&self) -> usize {
+        GenerationalHeapIn::num_allocated_blocks(self)
+    }
+
+    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        GenerationalHeapIn::allocated_block_ptr(self, block)
+    }
+
+    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
+        GenerationalHeapIn::blocks_num_copies(self)
+    }
+
+    fn malloc<T: Tracer>(&mut self, num_words: usize, tracer: &T) -> Result<Pointer, HeapError> {
+        GenerationalHeapIn::malloc(self, num_words, tracer)
+    }
+
+    fn assert_no_strays(&self) {
+        GenerationalHeapIn::assert_no_strays(self)
+    }
+}
+
+impl<
+        const NURSERY_SIZE: usize,
+        const MAX_BLOCKS: usize,
+        const MAX_COPIES: usize,
+        O: HeapObserver,
+        P: TriggerPolicy,
+        const CARD_SIZE: usize,
+        const CARDS: usize,
+        const OLD_SIZE: usize,
+    >
+    EpochCheckedHeap for GenerationalHeap<NURSERY_SIZE, MAX_BLOCKS, MAX_COPIES, O, P, CARD_SIZE, CARDS, OLD_SIZE>
+{
+    fn block_epoch(&self, block: usize) -> Option<usize> {
+        self.block_info.epoch(block)
+    }
+}
+
+impl<
+        W0: Buffer<u64>,
+        W1: Buffer<u64>,
+        B: Buffer<BlockSlot<GenerationalSlot>>,
+        M: Buffer<bool>,
+        const MAX_COPIES: usize,
+        O: HeapObserver,
+        P: TriggerPolicy,
+        const CARD_SIZE: usize,
+        const CARDS: usize,
+    >
+    PoisonCheckedHeap for GenerationalHeapIn<W0, W1, B, M, MAX_COPIES, O, P, CARD_SIZE, CARDS>
+{
+    fn poison_checked_load(&self, p: Pointer) -> Result<u64, LoadError> {
+        let (heap, gen) = self.heap_and_gen_for(p.block_num())?;
+        let address = self.block_info.address(p)?;
+        if gen == 0 {
+            self.gen_0[heap].checked_load(address)
+        } else {
+            self.gen_1[heap].checked_load(address)
+        }
+    }
+}
+
+impl<
+        W0: Buffer<u64>,
+        W1: Buffer<u64>,
+        B: Buffer<BlockSlot<GenerationalSlot>>,
+        M: Buffer<bool>,
+        const MAX_COPIES: usize,
+        O: HeapObserver,
+        P: TriggerPolicy,
+        const CARD_SIZE: usize,
+        const CARDS: usize,
+    >
+    CollectableHeap for GenerationalHeapIn<W0, W1, B, M, MAX_COPIES, O, P, CARD_SIZE, CARDS>
+{
+    /// Collects both generations, regardless of whether generation 1 has room for promotions.
+    fn collect<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError> {
+        self.collect_gen_0(tracer, true, CollectionTrigger::Explicit)
+    }
+
+    /// Collects generation 0, collecting generation 1 only if promotions do not fit.
+    fn collect_minor<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError> {
+        self.collect_gen_0(tracer, false, CollectionTrigger::Explicit)
+    }
+}
+
+impl<
+        W0: Buffer<u64>,
+        W1: Buffer<u64>,
+        B: Buffer<BlockSlot<GenerationalSlot>>,
+        M: Buffer<bool>,
+        const MAX_COPIES: usize,
+        O: HeapObserver,
+        P: TriggerPolicy,
+        const CARD_SIZE: usize,
+        const CARDS: usize,
+    >
+    VerifiableHeap for GenerationalHeapIn<W0, W1, B, M, MAX_COPIES, O, P, CARD_SIZE, CARDS>
+{
+    fn verify(&self) -> Violations {
+        // A collection copies each block at most once per generation it collects.
+        let totals = self.stats.totals();
+        let max_copies = totals.collections + totals.gen_1_collections;
+        let mut violations = self.block_info.verify(max_copies, |block, _| {
+            if self.in_gen_1(block) {
+                (1, self.gen_1[self.active_gen_1].next_address)
+            } else {
+                (0, self.gen_0[self.active_gen_0].next_address)
+            }
+        });
+        // A block is promoted by the collection after it reaches the threshold, which never
+        // exceeds `MAX_COPIES`, and promoting it copies it once more.
+        for (block, copies) in self.blocks_num_copies() {
+            let tenured = self.in_gen_1(block);
+            if tenured && copies <= self.lowest_threshold {
+                violations.push(Violation::Generation(block, copies, 1));
+            } else if !tenured && copies > MAX_COPIES {
+                violations.push(Violation::Generation(block, copies, 0));
+            }
+        }
+        let inactive_0 = &self.gen_0[(self.active_gen_0 + 1) % 2];
+        let inactive_1 = &self.gen_1[(self.active_gen_1 + 1) % 2];
+        for next_address in [inactive_0.next_address, inactive_1.next_address] {
+            if next_address != 0 {
+                violations.push(Violation::Strays(next_address));
+            }
+        }
+        violations
+    }
+}
+
+/// How many times `MultiGenerationalHeap` copies a block within each generation before
+/// promoting it, unless told otherwise.
+const DEFAULT_MAX_COPIES: usize = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GenerationError {
+    Heap(HeapError),
+    /// Generation requested, number of generations.
+    NoSuchGeneration(usize, usize),
+}
+
+impl From<HeapError> for GenerationError {
+    fn from(e: HeapError) -> Self {
+        GenerationError::Heap(e)
+    }
+}
+
+// What `MultiGenerationalHeap` keeps in the block table for each block.
+#[derive(Copy, Clone, Debug, Default)]
+struct MultiGenerationalSlot {
+    generation: usize,
+    // The block's `num_times_copied` when it entered its generation.
+    entered_at: usize,
+    // Whether the block may refer to a younger generation than its own.
+    remembered: bool,
+    // Set once the current collection has copied this block.
+    copied: bool,
+    next_scan: usize,
+}
+
+impl ScanLink for MultiGenerationalSlot {
+    fn next_scan(&mut self) -> &mut usize {
+        &mut self.next_scan
+    }
+}
+
+/// Generalizes `GenerationalHeap` to `GENERATIONS` generations, each a pair of
+/// `HEAP_SIZE`-word semispaces. A block is promoted to the next generation once it has
+/// been copied `max_copies` times within its own, and the oldest generation keeps its
+/// blocks. Collecting generation `k` also collects every younger generation, and when
+/// the blocks promoted out of `k` would not fit in generation `k + 1`, that generation
+/// is collected as well, and so on up to the oldest.
+///
+/// As in `GenerationalHeap`, a write barrier in `store` keeps a remembered set, here of the
+/// blocks that may refer to a younger generation than their own. Collecting generation `k`
+/// starts from the remembered blocks of older generations as well as the roots and looks
+/// inside no other block of those generations. There is no card marking, which would need
+/// a card table for every generation.
+#[derive(Copy, Clone, Debug)]
+pub struct MultiGenerationalHeap<
+    const HEAP_SIZE: usize,
+    const MAX_BLOCKS: usize,
+    const GENERATIONS: usize,
+    O: HeapObserver = NoObserver,
+    P: TriggerPolicy = CollectOnFailure,
+> {
+    heaps: [[FixedRamHeap<HEAP_SIZE>; 2]; GENERATIONS],
+    active: [usize; GENERATIONS],
+    max_copies: [usize; GENERATIONS],
+    block_info: FixedBlockTable<MAX_BLOCKS, MultiGenerationalSlot>,
+    stress_mode: StressMode,
+    stats: GcStats,
+    observer: O,
+    trigger_policy: P,
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, const GENERATIONS: usize, O: HeapObserver, P: TriggerPolicy>
+    MultiGenerationalHeap<HEAP_SIZE, MAX_BLOCKS, GENERATIONS, O, P>
+{
+    /// Sets how many times a block is copied within `generation` before being promoted.
+    pub fn set_max_copies(&mut self, generation: usize, max_copies: usize) -> Result<(), GenerationError> {
+        match self.max_copies.get_mut(generation) {
+            Some(entry) => {
+                *entry = max_copies;
+                Ok(())
+            }
+            None => Err(GenerationError::NoSuchGeneration(generation, GENERATIONS)),
+        }
+    }
+
+    pub fn generation_of(&self, block: usize) -> Option<usize> {
+        self.block_info.allocated_block_ptr(block).map(|_| self.block_info.collector(block).generation)
+    }
+
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    pub fn set_stress_mode(&mut self, stress_mode: StressMode) {
+        self.stress_mode = stress_mode;
+    }
+
+    pub fn trigger_policy(&self) -> &P {
+        &self.trigger_policy
+    }
+
+    pub fn set_trigger_policy(&mut self, trigger_policy: P) {
+        self.trigger_policy = trigger_policy;
+    }
+
+    pub fn stats(&self) -> &GcStats {
+        &self.stats
+    }
+
+    /// Collects generations `0..=generation`, along with any older ones that promotions overflow.
+    pub fn collect_generation<T: Tracer>(
+        &mut self,
+        tracer: &T,
+        generation: usize,
+    ) -> Result<CollectionSummary, GenerationError> {
+        if generation >= GENERATIONS {
+            return Err(GenerationError::NoSuchGeneration(generation, GENERATIONS));
+        }
+        self.collect_for(tracer, generation, CollectionTrigger::Explicit)
+            .map_err(GenerationError::Heap)
+    }
+
+    fn occupancy(&self) -> HeapOccupancy {
+        HeapOccupancy {
+            blocks_in_use: self.block_info.num_allocated_blocks(),
+            max_blocks: MAX_BLOCKS,
+            words_in_use: self.heaps[0][self.active[0]].next_address,
+            heap_size: HEAP_SIZE,
+        }
+    }
+
+    fn heap_for(&self, block: usize) -> &FixedRamHeap<HEAP_SIZE> {
+        let generation = self.block_info.collector(block).generation;
+        &self.heaps[generation][self.active[generation]]
+    }
+
+    /// Whether `word` refers to a block in a younger generation than `generation`.
+    fn refers_younger(&self, word: u64, generation: usize) -> bool {
+        decode_pointer(word)
+            .is_some_and(|p| self.block_info.address(p).is_ok() && self.block_info.collector(p.block_num()).generation < generation)
+    }
+
+    /// Whether `block` holds a reference to a block in a younger generation than its own.
+    fn refers_to_younger(&self, block: usize) -> bool {
+        let info = self.block_info[block].unwrap();
+        let words = &self.heap_for(block).heap[info.start..info.start + info.size];
+        words.iter().any(|word| self.refers_younger(*word, self.block_info.collector(block).generation))
+    }
+
+    /// The generation `block` moves to the next time its generation is collected.
+    fn destination(&self, block: usize) -> usize {
+        let MultiGenerationalSlot { generation, entered_at, .. } = *self.block_info.collector(block);
+        let copies = self.block_info[block].unwrap().num_times_copied - entered_at;
+        if generation + 1 < GENERATIONS && copies >= self.max_copies[generation] {
+            generation + 1
+        } else {
+            generation
+        }
+    }
+
+    /// The youngest generation, no younger than `oldest`, whose collection along with every
+    /// younger one leaves room for all of their survivors.
+    fn oldest_to_collect(&self, used_blocks: &[bool; MAX_BLOCKS], oldest: usize) -> Option<usize> {
+        (oldest..GENERATIONS).find(|&oldest| {
+            let mut incoming = [0; GENERATIONS];
+            for block in self.block_info.blocks_in_use().filter(|b| used_blocks[*b]) {
+                if self.block_info.collector(block).generation <= oldest {
+                    incoming[self.destination(block)] += self.block_info[block].unwrap().size;
+                }
+            }
+            let next = oldest + 1;
+            incoming[..next].iter().all(|words| *words <= HEAP_SIZE)
+                && (next == GENERATIONS
+                    || self.heaps[next][self.active[next]].next_address + incoming[next] <= HEAP_SIZE)
+        })
+    }
+
+    /// Marks the blocks reachable from `tracer` for a collection of generations `0..=oldest`.
+    /// Every block of an older generation is marked, but only the remembered ones are scanned.
+    fn trace<T: Tracer>(&mut self, tracer: &T, oldest: usize) -> Result<[bool; MAX_BLOCKS], HeapError> {
+        let mut used_blocks = [false; MAX_BLOCKS];
+        tracer.trace(&mut used_blocks);
+        let (heaps, active) = (&self.heaps, &self.active);
+        for block in self.block_info.blocks_in_use() {
+            let slot = self.block_info.collector(block);
+            used_blocks[block] |= slot.generation > oldest && slot.remembered;
+        }
+        self.block_info.trace_interior(
+            &mut used_blocks,
+            |_, slot| slot.generation <= oldest || slot.remembered,
+            |_, slot, address| heaps[slot.generation][active[slot.generation]].load(address),
+        )?;
+        for block in self.block_info.blocks_in_use() {
+            used_blocks[block] |= self.block_info.collector(block).generation > oldest;
+        }
+        Ok(used_blocks)
+    }
+
+    /// Rebuilds the remembered set after a collection. Blocks that were copied may have
+    /// changed generation, as may the blocks they refer to, while remembered blocks may
+    /// no longer refer to a younger generation. No other block can have changed.
+    fn refresh_barrier(&mut self) {
+        for block in 0..MAX_BLOCKS {
+            let slot = *self.block_info.collector(block);
+            let candidate = slot.copied || slot.remembered;
+            self.block_info.collector_mut(block).remembered =
+                candidate && self.block_info[block].is_some() && self.refers_to_younger(block);
+        }
+    }
+
+    fn collect_for<T: Tracer>(
+        &mut self,
+        tracer: &T,
+        oldest: usize,
+        trigger: CollectionTrigger,
+    ) -> Result<CollectionSummary, HeapError> {
+        let before = self.block_info.usage();
+
+        // As in `GenerationalHeap`, a collection that cannot succeed fails before anything
+        // moves. Collecting an older generation than asked needs to know which of its blocks
+        // are live, so the trace is then redone for that generation.
+        let mut oldest = oldest;
+        let used_blocks = loop {
+            let used_blocks = self.trace(tracer, oldest)?;
+            match self.oldest_to_collect(&used_blocks, oldest) {
+                None => return Err(HeapError::OutOfMemory),
+                Some(needed) if needed == oldest => break used_blocks,
+                Some(needed) => oldest = needed,
+            }
+        };
+        self.observer.on_collect_start();
+
+        let mut words_copied = 0;
+        let mut promotions = 0;
+        for (block, &used) in used_blocks.iter().enumerate() {
+            self.block_info.collector_mut(block).copied = false;
+            let info = match self.block_info[block] {
+                Some(info) if self.block_info.collector(block).generation <= oldest => info,
+                _ => continue,
+            };
+            if !used {
+                self.observer.on_block_freed(block, info.size);
+                self.block_info.set(block, None);
+                continue;
+            }
+            let (from, to) = (self.block_info.collector(block).generation, self.destination(block));
+            // Generations being collected receive survivors in to-space; the one above them
+            // receives its promotions in the space it is already using.
+            let dest = if to <= oldest { (self.active[to] + 1) % 2 } else { self.active[to] };
+            let heaps = self.heaps.as_flattened_mut();
+            let (src, dest) =
+                independent_elements_from(2 * from + self.active[from], 2 * to + dest, heaps).unwrap();
+            let new_info = src.copy(&info, dest).expect("the survivors fit");
+            self.block_info.set(block, Some(new_info));
+            let slot = self.block_info.collector_mut(block);
+            slot.copied = true;
+            words_copied += info.size;
+            if to != from {
+                promotions += 1;
+                (slot.generation, slot.entered_at) = (to, new_info.num_times_copied);
+                self.observer.on_promote(block, info.size);
+            } else {
+                self.observer.on_block_copied(block, info.size);
+            }
+        }
+        for generation in 0..=oldest {
+            let vacated = &mut self.heaps[generation][self.active[generation]];
+            vacated.clear();
+            if self.stress_mode == StressMode::CollectAndPoison {
+                vacated.poison();
+            }
+            self.active[generation] = (self.active[generation] + 1) % 2;
+        }
+        self.refresh_barrier();
+
+        let summary = CollectionSummary::new(before, self.block_info.usage());
+        self.stats.record(CollectionRecord {
+            trigger,
+            blocks_live: summary.blocks_live,
+            words_copied,
+            words_reclaimed: summary.words_reclaimed,
+            promotions,
+            gen_1_collected: oldest > 0,
+            cards_scanned: 0,
+        });
+        self.trigger_policy.on_collect();
+        self.observer.on_collect_end(summary);
+        Ok(summary)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, const GENERATIONS: usize, O: HeapObserver, P: TriggerPolicy>
+    GarbageCollectingHeap for MultiGenerationalHeap<HEAP_SIZE, MAX_BLOCKS, GENERATIONS, O, P>
+{
+    fn new() -> Self {
+        const {
+            assert!(GENERATIONS > 0, "a heap needs at least one generation");
+        }
+        Self {
+            heaps: [[RamHeap::new(); 2]; GENERATIONS],
+            active: [0; GENERATIONS],
+            max_copies: [DEFAULT_MAX_COPIES; GENERATIONS],
+            block_info: BlockTable::new(),
+            stress_mode: StressMode::Off,
+            stats: GcStats::default(),
+            observer: O::default(),
+            trigger_policy: P::default(),
+        }
+    }
+
+    fn address(&self, p: Pointer) -> Result<usize, HeapError> {
+        self.block_info.address(p)
+    }
+
+    fn load(&self, p: Pointer) -> Result<u64, HeapError> {
+        self.block_info
+            .address(p)
+            .and_then(|address| self.heap_for(p.block_num()).load(address))
+    }
+
+    fn store(&mut self, p: Pointer, value: u64) -> Result<(), HeapError> {
+        let address = self.block_info.address(p)?;
+        let generation = self.block_info.collector(p.block_num()).generation;
+        // The write barrier: a block that now refers to a younger generation is remembered.
+        if self.refers_younger(value, generation) {
+            self.block_info.collector_mut(p.block_num()).remembered = true;
+        }
+        self.heaps[generation][self.active[generation]].store(address, value)
+    }
+
+    fn blocks_in_use(&self) -> impl Iterator<Item = usize> {
+        self.block_info.blocks_in_use()
+    }
+
+    fn num_allocated_blocks(&self) -> usize {
+        self.block_info.num_allocated_blocks()
+    }
+
+    fn allocated_block_ptr(&self, block: usize) -> Option<Pointer> {
+        self.block_info.allocated_block_ptr(block)
+    }
+
+    fn blocks_num_copies(&self) -> impl Iterator<Item = (usize, usize)> {
+        self.block_info.blocks_num_copies()
+    }
+
+    fn malloc<T: Tracer>(&mut self, num_words: usize, tracer: &T) -> Result<Pointer, HeapError> {
+        if num_words == 0 {
+            return Err(HeapError::ZeroSizeRequest);
+        }
+        if self.stress_mode != StressMode::Off {
+            self.collect_for(tracer, GENERATIONS - 1, CollectionTrigger::Stress)?;
+        } else if self.trigger_policy.should_collect(self.occupancy(), num_words) {
+            // An early collection is only an optimisation, so the request may still
+            // succeed without it.
+            let _ = self.collect_for(tracer, 0, CollectionTrigger::Policy);
+        }
+        let block = match self.block_info.available_block() {
+            Some(block) => block,
+            None => {
+                self.collect_for(tracer, 0, CollectionTrigger::OutOfBlocks)?;
+                match self.block_info.available_block() {
+                    Some(block) => block,
+                    None => return Err(HeapError::OutOfBlocks),
+                }
+            }
+        };
+        let start = match self.heaps[0][self.active[0]].malloc(num_words) {
+            Ok(start) => start,
+            Err(_) => {
+                self.collect_for(tracer, 0, CollectionTrigger::OutOfMemory)?;
+                match self.heaps[0][self.active[0]].malloc(num_words) {
+                    Ok(start) => start,
+                    Err(_) => return Err(HeapError::OutOfMemory),
+                }
+            }
+        };
+
+        self.block_info.set(block, Some(BlockInfo { start, size: num_words, num_times_copied: 0 }));
+        *self.block_info.collector_mut(block) = MultiGenerationalSlot::default();
+        self.observer.on_malloc(block, num_words);
+        self.trigger_policy.on_malloc(num_words);
+        Ok(Pointer::new(block, num_words))
+    }
+
+    fn assert_no_strays(&self) {
+        for (heaps, &active) in self.heaps.iter().zip(self.active.iter()) {
+            assert!(heaps[(active + 1) % 2].next_address == 0);
+        }
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, const GENERATIONS: usize, O: HeapObserver, P: TriggerPolicy>
+    EpochCheckedHeap for MultiGenerationalHeap<HEAP_SIZE, MAX_BLOCKS, GENERATIONS, O, P>
+{
+    fn block_epoch(&self, block: usize) -> Option<usize> {
+        self.block_info.epoch(block)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, const GENERATIONS: usize, O: HeapObserver, P: TriggerPolicy>
+    PoisonCheckedHeap for MultiGenerationalHeap<HEAP_SIZE, MAX_BLOCKS, GENERATIONS, O, P>
+{
+    fn poison_checked_load(&self, p: Pointer) -> Result<u64, LoadError> {
+        self.heap_for(p.block_num()).checked_load(self.block_info.address(p)?)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, const GENERATIONS: usize, O: HeapObserver, P: TriggerPolicy>
+    CollectableHeap for MultiGenerationalHeap<HEAP_SIZE, MAX_BLOCKS, GENERATIONS, O, P>
+{
+    /// Collects every generation.
+    fn collect<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError> {
+        self.collect_for(tracer, GENERATIONS - 1, CollectionTrigger::Explicit)
+    }
+
+    /// Collects generation 0, along with any older ones that promotions overflow.
+    fn collect_minor<T: Tracer>(&mut self, tracer: &T) -> Result<CollectionSummary, HeapError> {
+        self.collect_for(tracer, 0, CollectionTrigger::Explicit)
+    }
+}
+
+impl<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, const GENERATIONS: usize, O: HeapObserver, P: TriggerPolicy>
+    VerifiableHeap for MultiGenerationalHeap<HEAP_SIZE, MAX_BLOCKS, GENERATIONS, O, P>
+{
+    fn verify(&self) -> Violations {
+        let collections = self.stats.totals().collections;
+        let mut violations = self.block_info.verify(collections, |block, _| {
+            (self.block_info.collector(block).generation, self.heap_for(block).next_address)
+        });
+        for (heaps, &active) in self.heaps.iter().zip(self.active.iter()) {
+            if heaps[(active + 1) % 2].next_address != 0 {
+                violations.push(Violation::Strays(heaps[(active + 1) % 2].next_address));
+            }
+        }
+        violations
+    }
+}
+
+/// Backing memory for a heap: an array it owns, a caller-supplied slice, or with the
+/// `alloc` feature a `Vec` that the heap can grow.
+pub trait Buffer<T>: AsRef<[T]> + AsMut<[T]> {
+    /// Resizes to `len` elements, returning false if this buffer cannot grow.
+    fn grow_to(&mut self, len: usize, fill: T) -> bool;
+}
+
+impl<T, const N: usize> Buffer<T> for [T; N] {
+    fn grow_to(&mut self, _: usize, _: T) -> bool {
+        false
+    }
+}
+
+impl<T> Buffer<T> for &mut [T] {
+    fn grow_to(&mut self, _: usize, _: T) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Clone> Buffer<T> for Vec<T> {
+    fn grow_to(&mut self, len: usize, fill: T) -> bool {
+        self.resize(len, fill);
+        true
+    }
+}
+
+impl<W: Buffer<u64>> RamHeap<W> {
+    fn grow(&mut self, size: usize) -> bool {
+        self.heap.grow_to(size, UNUSED_WORD)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Growth {
+    /// Double the words or blocks, whichever ran out.
+    Double,
+    /// Add this many words or blocks, whichever ran out.
+    Increment(usize),
+}
+
+/// How `CopyingHeap` and `GenerationalHeap` enlarge themselves when an allocation fails
+/// even after a collection. Only storage that can grow is enlarged, such as the `Vec`s of
+/// `VecCopyingHeap` and `VecGenerationalHeap`. `max_words` caps the size of each semispace
+/// and `max_blocks` the size of the block table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GrowthPolicy {
+    pub growth: Growth,
+    pub max_words: usize,
+    pub max_blocks: usize,
+}
+
+impl GrowthPolicy {
+    /// The size that `current` grows to in order to hold `needed`, or `None` past `max`.
+    /// The result is never smaller than `current`, which may already exceed `max`.
+    fn grown(&self, current: usize, needed: usize, max: usize) -> Option<usize> {
+        if current >= needed {
+            return Some(current);
+        }
+        if needed > max {
+            return None;
+        }
+        let mut size = current;
+        while size < needed {
+            size = match self.growth {
+                Growth::Double => size.max(1) * 2,
+                Growth::Increment(n) => size + n.max(1),
+            };
+        }
+        Some(size.min(max))
+    }
+
+    /// Grows every heap in `heaps` alike, as semispaces must stay the same size.
+    fn grow_words<W: Buffer<u64>>(&self, heaps: &mut [RamHeap<W>], needed: usize) -> bool {
+        match self.grown(heaps[0].size(), needed, self.max_words) {
+            Some(size) => heaps.iter_mut().all(|heap| heap.grow(size)),
+            None => false,
+        }
+    }
+
+    /// Grows a block table and its marks alike, as they must stay the same length.
+    fn grow_blocks<C: Copy + Default, B: Buffer<BlockSlot<C>>, M: Buffer<bool>>(
+        &self,
+        block_info: &mut BlockTable<B, C>,
+        marks: &mut M,
+    ) -> bool {
+        let len = marks.as_ref().len();
+        match self.grown(len, len + 1, self.max_blocks) {
+            Some(len) => block_info.grow(len) && marks.grow_to(len, false),
+            None => false,
+        }
+    }
+}
+
+/// `OnceAndDoneHeap` on caller-supplied memory, for heaps too large for the stack or
+/// sized at run time. Such heaps cannot be built by `GarbageCollectingHeap::new`, so
+/// they provide its operations as inherent methods and do not implement `EpochCheckedHeap`.
+pub type SliceOnceAndDoneHeap<'a, O = NoObserver> = OnceAndDoneHeapIn<&'a mut [u64], &'a mut [BlockSlot], O>;
+
+impl<'a, O: HeapObserver> SliceOnceAndDoneHeap<'a, O> {
+    pub fn new(words: &'a mut [u64], blocks: &'a mut [BlockSlot]) -> Self {
+        Self::in_storage(words, blocks)
+    }
+}
+
+/// `CopyingHeap` on caller-supplied memory, for heaps too large for the stack.
+pub type SliceCopyingHeap<'a, O = NoObserver, P = CollectOnFailure> =
+    CopyingHeapIn<&'a mut [u64], &'a mut [BlockSlot<CopyingSlot>], &'a mut [bool], O, P>;
+
+impl<'a, O: HeapObserver, P: TriggerPolicy> SliceCopyingHeap<'a, O, P> {
+    /// Splits `words` into two equal semispaces, leaving out the last word if there is an
+    /// odd number. `marks` must be as long as `blocks`.
+    pub fn new(words: &'a mut [u64], blocks: &'a mut [BlockSlot<CopyingSlot>], marks: &'a mut [bool]) -> Self {
+        let half = words.len() / 2;
+        let (first, rest) = words.split_at_mut(half);
+        Self::in_storage([first, &mut rest[..half]], blocks, marks)
+    }
+}
+
+/// `CopyingHeap` on memory it allocates, growing when a collection does not free enough.
+#[cfg(feature = "alloc")]
+pub type VecCopyingHeap<O = NoObserver, P = CollectOnFailure> = CopyingHeapIn<Vec<u64>, Vec<BlockSlot<CopyingSlot>>, Vec<bool>, O, P>;
+
+#[cfg(feature = "alloc")]
+impl<O: HeapObserver, P: TriggerPolicy> VecCopyingHeap<O, P> {
+    /// Starts with `words` words in each semispace and room for `blocks` blocks.
+    pub fn new(words: usize, blocks: usize, growth: GrowthPolicy) -> Self {
+        let mut heap = Self::in_storage(
+            [vec![0; words], vec![0; words]],
+            vec![BlockSlot::default(); blocks],
+            vec![false; blocks],
+        );
+        heap.set_growth_policy(Some(growth));
+        heap
+    }
+}
+
+/// `GenerationalHeap` on caller-supplied memory, for heaps too large for the stack.
+pub type SliceGenerationalHeap<'a, const MAX_COPIES: usize, O = NoObserver, P = CollectOnFailure> =
+    GenerationalHeapIn<&'a mut [u64], &'a mut [u64], &'a mut [BlockSlot<GenerationalSlot>], &'a mut [bool], MAX_COPIES, O, P>;
+
+impl<'a, const MAX_COPIES: usize, O: HeapObserver, P: TriggerPolicy> SliceGenerationalHeap<'a, MAX_COPIES, O, P> {
+    /// Splits `words` into four equal semispaces, leaving out any words left over.
+    /// `marks` must be as long as `blocks`.
+    pub fn new(words: &'a mut [u64], blocks: &'a mut [BlockSlot<GenerationalSlot>], marks: &'a mut [bool]) -> Self {
+        let quarter = words.len() / 4;
+        let (gen_0, gen_1) = words.split_at_mut(2 * quarter);
+        let (gen_0_a, gen_0_b) = gen_0.split_at_mut(quarter);
+        let (gen_1_a, gen_1_b) = gen_1.split_at_mut(quarter);
+        Self::in_storage([gen_0_a, gen_0_b], [gen_1_a, &mut gen_1_b[..quarter]], blocks, marks)
+    }
+}
+
+/// `GenerationalHeap` on memory it allocates, growing when a collection does not free enough.
+/// It uses a remembered set, as a card table cannot grow.
+#[cfg(feature = "alloc")]
+pub type VecGenerationalHeap<const MAX_COPIES: usize, O = NoObserver, P = CollectOnFailure> =
+    GenerationalHeapIn<Vec<u64>, Vec<u64>, Vec<BlockSlot<GenerationalSlot>>, Vec<bool>, MAX_COPIES, O, P>;
+
+#[cfg(feature = "alloc")]
+impl<const MAX_COPIES: usize, O: HeapObserver, P: TriggerPolicy> VecGenerationalHeap<MAX_COPIES, O, P> {
+    /// Starts with `words` words in each semispace and room for `blocks` blocks.
+    pub fn new(words: usize, blocks: usize, growth: GrowthPolicy) -> Self {
+        let mut heap = Self::in_storage(
+            [vec![0; words], vec![0; words]],
+            [vec![0; words], vec![0; words]],
+            vec![BlockSlot::default(); blocks],
+            vec![false; blocks],
+        );
+        heap.set_growth_policy(Some(growth));
+        heap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use core::fmt::Debug;
+
+    use super::*;
+    use test_tracer::TestTracer;
+
+    const HEAP_SIZE: usize = 96;
+    const MAX_BLOCKS: usize = 12;
+
+    // Level 1 Unit Tests
+
+    #[test]
+    fn block_table_test() {
+        let mut table = FixedBlockTable::<5>::new();
+        assert_eq!(table.available_block().unwrap(), 0);
+        table.set(0, Some(BlockInfo { start: 3, size: 2, num_times_copied: 0 }));
+        assert_eq!(table.available_block().unwrap(), 1);
+        table.set(2, Some(BlockInfo { start: 5, size: 3, num_times_copied: 0 }));
+        assert_eq!(table.available_block().unwrap(), 1);
+        table.set(1, Some(BlockInfo { start: 8, size: 2, num_times_copied: 0 }));
+        assert_eq!(table.available_block().unwrap(), 3);
+
+        let p = Pointer::new(0, 2);
+        for (i, ptr) in p.iter().enumerate() {
+            assert_eq!(table.address(ptr).unwrap(), i + 3);
+        }
+        let end_ptr = p.iter().last().unwrap();
+        table.set(0, Some(BlockInfo {start: 3, size: 1, num_times_copied: 0}));
+        assert_eq!(table.address(p), Err(HeapError::MisalignedPointer(2, 1, 0)));
+        assert_eq!(table.address(end_ptr), Err(HeapError::OffsetTooBig(1, 0, 1)));
+
+        let p = Pointer::new(5, 2);
+        assert_eq!(table.address(p), Err(HeapError::IllegalBlock(5, 4)));
+
+        let p = Pointer::new(3, 2);
+        assert_eq!(table.address(p), Err(HeapError::UnallocatedBlock(3)));
+    }
+
+    #[test]
+    fn block_table_bitmap_test() {
+        // 130 blocks take three words of bits, the last of them mostly past the end.
+        let mut table = FixedBlockTable::<130>::new();
+        for b in 0..130 {
+            assert_eq!(table.available_block().unwrap(), b);
+            table.set(b, Some(BlockInfo { start: b, size: 1, num_times_copied: 0 }));
+        }
+        assert_eq!(table.available_block(), None);
+
+        for b in [129, 64, 3, 70] {
+            table.set(b, None);
+        }
+        assert_eq!(table.num_allocated_blocks(), 126);
+        let in_use = table.blocks_in_use().collect::<Vec<_>>();
+        assert_eq!(in_use.len(), 126);
+        assert!(in_use.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(in_use[60..64], [61, 62, 63, 65]);
+        for b in [3, 64, 70, 129] {
+            assert_eq!(table.available_block().unwrap(), b);
+            table.set(b, Some(BlockInfo { start: b, size: 1, num_times_copied: 0 }));
+        }
+        assert_eq!(table.available_block(), None);
+        assert_eq!(table.num_allocated_blocks(), 130);
+        assert!(table.verify(0, |_, _| (0, 130)).is_empty());
+    }
+
+    #[test]
+    fn basic_allocation_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = OnceAndDoneHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+    }
+
+    #[test]
+    fn out_of_blocks_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = OnceAndDoneHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_out_of_blocks(&mut allocator, &mut tracer);
+    }
+
+    #[test]
+    fn test_bad_address_error() {
+        let mut allocator = FixedRamHeap::<HEAP_SIZE>::new();
+        match allocator.load(HEAP_SIZE + 1) {
+            Ok(_) => panic!("This should have been an IllegalAddress error."),
+            Err(e) => assert_eq!(e, HeapError::IllegalAddress(HEAP_SIZE + 1, 0))
+        }
+
+        allocator.malloc(96).unwrap();
+        match allocator.load(HEAP_SIZE + 1) {
+            Ok(_) => panic!("This should have been an IllegalAddress error."),
+            Err(e) => assert_eq!(e, HeapError::IllegalAddress(HEAP_SIZE + 1, HEAP_SIZE))
+        }
+    }
+
+    #[test]
+    fn free_list_fit_test() {
+        for (policy, expected) in [
+            (FitPolicy::FirstFit, [10, 30, 15]),
+            (FitPolicy::BestFit, [50, 30, 10]),
+            (FitPolicy::NextFit, [10, 30, 50]),
+        ] {
+            let mut allocator = FixedRamHeap::<HEAP_SIZE>::new();
+            allocator.fit_policy = policy;
+            allocator.malloc(HEAP_SIZE).unwrap();
+            allocator.free(10, 8);
+            allocator.free(30, 4);
+            allocator.free(50, 5);
+            assert_eq!(allocator.malloc(5).unwrap(), expected[0]);
+            assert_eq!(allocator.malloc(4).unwrap(), expected[1]);
+            assert_eq!(allocator.malloc(1).unwrap(), expected[2]);
+        }
+    }
+
+    #[test]
+    fn free_list_coalesce_test() {
+        let mut allocator = FixedRamHeap::<HEAP_SIZE>::new();
+        allocator.malloc(40).unwrap();
+        allocator.free(10, 5);
+        allocator.free(20, 5);
+        allocator.free(15, 5);
+        assert_eq!(allocator.holes().collect::<Vec<_>>(), vec![(10, 15)]);
+
+        allocator.free(30, 10);
+        assert_eq!(allocator.next_address, 30);
+        allocator.free(25, 5);
+        assert_eq!(allocator.holes().count(), 0);
+        assert_eq!(allocator.next_address, 10);
+    }
+
+    // Level 2 Unit Tests
+
+    #[test]
+    fn deallocation_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        allocator.assert_no_strays();
+        test_out_of_blocks(&mut allocator, &mut tracer);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+    }
+
+    #[test]
+    fn collection_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_out_of_blocks(&mut allocator, &mut tracer);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_force_collection(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        allocator.assert_no_strays();
+    }
+
+    #[test]
+    fn full_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_force_collection(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_fill_ram(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        allocator.assert_no_strays();
+        test_out_of_ram(&mut allocator, &mut tracer);
+    }
+
+    #[test]
+    fn test_no_blocks_error() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_out_of_blocks(&mut allocator, &mut tracer);
+    }
+
+    #[test]
+    fn test_zero_size_error() {
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let tracer = TestTracer::default();
+        match allocator.malloc(0, &tracer) {
+            Ok(_) => panic!("This should have been a zero-size error"),
+            Err(e) => assert_eq!(e, HeapError::ZeroSizeRequest),
+        }
+    }
+
+    #[test]
+    fn test_illegal_block_error() {
+        let allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let bad_ptr = Pointer::new(MAX_BLOCKS, 1);
+        match allocator.load(bad_ptr) {
+            Ok(_) => panic!("This should have been an error"),
+            Err(e) => assert_eq!(e, HeapError::IllegalBlock(MAX_BLOCKS, MAX_BLOCKS - 1))
+        }
+    }
+
+    #[test]
+    fn test_unallocated_block_error() {
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let tracer = TestTracer::default();
+        let p = allocator.malloc(1, &tracer).unwrap();
+        let bad_ptr = Pointer::new(p.block_num() + 1, 1);
+        match allocator.load(bad_ptr) {
+            Ok(_) => panic!("This should have been an UnallocatedBlock error"),
+            Err(e) => assert_eq!(e, HeapError::UnallocatedBlock(bad_ptr.block_num()))
+        }
+    }
+
+    #[test]
+    fn test_offset_error() {
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        let p = tracer.allocate_next(HEAP_SIZE, &mut allocator).unwrap();
+        let s = p.iter().skip(1).next().unwrap();
+        tracer.deallocate_next().unwrap();
+        tracer.allocate_next(1, &mut allocator).unwrap();
+        let q = tracer.allocate_next(1, &mut allocator).unwrap();
+        assert_eq!(p.block_num(), q.block_num());
+        match allocator.load(s) {
+            Ok(_) => panic!("This should have been an OffsetTooBig error"),
+            Err(e) => assert_eq!(e, HeapError::OffsetTooBig(1, p.block_num(), 1))
+        }
+    }
+
+    #[test]
+    fn test_misaligned_pointer_error() {
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        let p = tracer.allocate_next(HEAP_SIZE, &mut allocator).unwrap();
+        tracer.deallocate_next().unwrap();
+        tracer.allocate_next(1, &mut allocator).unwrap();
+        let q = tracer.allocate_next(1, &mut allocator).unwrap();
+        assert_eq!(p.block_num(), q.block_num());
+        match allocator.load(p) {
+            Ok(_) => panic!("This should have been a MisalignedPointer error"),
+            Err(e) => assert_eq!(e, HeapError::MisalignedPointer(HEAP_SIZE, 1, p.block_num()))
+        }
+    }
+
+    #[test]
+    fn mark_sweep_collection_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = MarkSweepHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_out_of_blocks(&mut allocator, &mut tracer);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_force_collection(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_fill_ram(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        allocator.assert_no_strays();
+        test_load_store(&blocks2ptrs, &mut allocator);
+    }
+
+    #[test]
+    fn mark_sweep_reuse_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = MarkSweepHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+
+        // Blocks 1 and 3 were swept, so their holes are reused in address order.
+        let p = tracer.allocate_next(10, &mut allocator).unwrap();
+        assert_eq!(allocator.address(p).unwrap(), 2);
+        let q = tracer.allocate_next(6, &mut allocator).unwrap();
+        assert_eq!(allocator.address(q).unwrap(), 16);
+        allocator.assert_no_strays();
+
+        // A request larger than any hole is satisfied by sliding the survivors together.
+        let r = tracer.allocate_next(25, &mut allocator).unwrap();
+        assert_eq!(allocator.address(r).unwrap(), 40);
+        allocator.assert_no_strays();
+        assert!(allocator.verify().is_empty());
+    }
+
+    #[test]
+    fn mark_compact_full_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = MarkCompactHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        allocator.assert_no_strays();
+        test_out_of_blocks(&mut allocator, &mut tracer);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_force_collection(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        allocator.assert_no_strays();
+        test_load_store(&blocks2ptrs, &mut allocator);
+        test_fill_ram(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        allocator.assert_no_strays();
+        test_out_of_ram(&mut allocator, &mut tracer);
+    }
+
+    #[test]
+    fn mark_compact_slide_test() {
+        let mut allocator = MarkCompactHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        let mut ptrs = vec![];
+        for (i, size) in [4, 8, 2, 6].iter().enumerate() {
+            let p = tracer.allocate_next(*size, &mut allocator).unwrap();
+            for pt in p.iter() {
+                allocator.store(pt, i as u64).unwrap();
+            }
+            ptrs.push(p);
+        }
+        tracer.deallocate_next().unwrap();
+        tracer.allocate_next(HEAP_SIZE - 16, &mut allocator).unwrap();
+
+        // Survivors keep their block numbers and relative order, packed from address 0.
+        assert_eq!(allocator.address(ptrs[1]).unwrap(), 0);
+        assert_eq!(allocator.address(ptrs[2]).unwrap(), 8);
+        assert_eq!(allocator.address(ptrs[3]).unwrap(), 10);
+        for (i, p) in ptrs.iter().enumerate().skip(1) {
+            for pt in p.iter() {
+                assert_eq!(allocator.load(pt).unwrap(), i as u64);
+            }
+        }
+        for (b, c) in allocator.blocks_num_copies() {
+            assert_eq!(c, if b == 4 { 0 } else { 1 });
+        }
+        allocator.assert_no_strays();
+    }
+
+    #[test]
+    fn segregated_collection_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = SegregatedHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_out_of_blocks(&mut allocator, &mut tracer);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_force_collection(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        allocator.assert_no_strays();
+        test_load_store(&blocks2ptrs, &mut allocator);
+    }
+
+    #[test]
+    fn segregated_size_class_test() {
+        let mut allocator = SegregatedHeap::<100, 120>::new();
+        let mut tracer = TestTracer::default();
+        let mut blocks2ptrs = HashMap::new();
+        allocate_many(40, &mut allocator, &mut tracer, &mut blocks2ptrs);
+        let mut freed = HashMap::new();
+        for _ in 0..4 {
+            let p = tracer.deallocate_next().unwrap();
+            freed.insert(p.len(), allocator.address(p).unwrap());
+        }
+
+        // Once the heap is full, each small request is served from its own size class.
+        for size in (1..=4).rev() {
+            let p = tracer.allocate_next(size, &mut allocator).unwrap();
+            assert_eq!(allocator.address(p).unwrap(), freed[&size]);
+        }
+        allocator.assert_no_strays();
+    }
+
+    #[test]
+    fn buddy_merge_test() {
+        let mut allocator = BuddyRamHeap::<HEAP_SIZE>::new();
+        assert_eq!(allocator.malloc(3).unwrap(), 64);
+        assert_eq!(allocator.malloc(64).unwrap(), 0);
+        assert_eq!(allocator.malloc(32), Err(HeapError::OutOfMemory));
+
+        let mut allocator = BuddyRamHeap::<128>::new();
+        assert_eq!(allocator.malloc(3).unwrap(), 0);
+        assert_eq!(allocator.malloc(1).unwrap(), 4);
+        assert_eq!(allocator.malloc(1).unwrap(), 5);
+        assert_eq!(allocator.malloc(32).unwrap(), 32);
+        assert_eq!(allocator.internal_fragmentation(), 1);
+        assert_eq!(allocator.malloc(64).unwrap(), 64);
+        assert_eq!(allocator.malloc(33), Err(HeapError::OutOfMemory));
+
+        allocator.free(4, 1);
+        allocator.free(0, 3);
+        allocator.free(5, 1);
+        assert_eq!(allocator.internal_fragmentation(), 0);
+        assert_eq!(allocator.malloc(32).unwrap(), 0);
+        assert_eq!(allocator.malloc(1), Err(HeapError::OutOfMemory));
+    }
+
+    #[test]
+    fn buddy_fragmentation_test() {
+        let mut allocator = BuddyHeap::<128, 120>::new();
+        let mut tracer = TestTracer::default();
+        let mut blocks2ptrs = HashMap::new();
+        allocate_many(40, &mut allocator, &mut tracer, &mut blocks2ptrs);
+        assert_eq!(allocator.internal_fragmentation(), 10);
+        allocator.assert_no_strays();
+
+        // After a collection frees everything, the buddies merge back into a single chunk.
+        while tracer.deallocate_next().is_some() {}
+        let p = tracer.allocate_next(128, &mut allocator).unwrap();
+        assert_eq!(allocator.address(p).unwrap(), 0);
+        assert_eq!(allocator.internal_fragmentation(), 0);
+        allocator.assert_no_strays();
+    }
+
+    #[test]
+    fn test_stale_pointer_error() {
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        let p = tracer.allocate_next(1, &mut allocator).unwrap();
+        let stale = allocator.epoch_pointer(p).unwrap();
+        allocator.checked_store(stale, 3).unwrap();
+        assert_eq!(allocator.checked_load(stale), Ok(3));
+        for _ in 1..MAX_BLOCKS {
+            tracer.allocate_next(1, &mut allocator).unwrap();
+        }
+        tracer.deallocate_next().unwrap();
+        let q = tracer.allocate_next(1, &mut allocator).unwrap();
+        assert_eq!(p, q);
+        let fresh = allocator.epoch_pointer(q).unwrap();
+        allocator.checked_store(fresh, 7).unwrap();
+
+        // The recycled block has the same size, so only the epoch exposes the stale pointer.
+        assert!(allocator.load(p).is_ok());
+        match allocator.checked_load(stale) {
+            Ok(_) => panic!("This should have been a StalePointer error"),
+            Err(e) => assert_eq!(e, EpochError::StalePointer(p.block_num(), 0, 1))
+        }
+        assert_eq!(allocator.checked_load(fresh), Ok(7));
+    }
+
+    #[test]
+    fn interior_pointer_test() {
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        test_interior_pointers(&mut allocator);
+        let mut allocator = GenerationalHeap::<HEAP_SIZE, MAX_BLOCKS, 2>::new();
+        test_interior_pointers(&mut allocator);
+    }
+
+    fn test_interior_pointers<H: GarbageCollectingHeap>(allocator: &mut H) {
+        let mut tracer = TestTracer::default();
+        let head = tracer.allocate_next(2, allocator).unwrap();
+        let middle = tracer.allocate_next(2, allocator).unwrap();
+        let tail = tracer.allocate_next(2, allocator).unwrap();
+        let garbage = tracer.allocate_next(2, allocator).unwrap();
+        allocator.store(head, encode_pointer(middle)).unwrap();
+        allocator.store(middle, encode_pointer(tail)).unwrap();
+        allocator.store(tail, 17).unwrap();
+        allocator.store(garbage, encode_pointer(head)).unwrap();
+
+        // Only the head of the list is reported as a root.
+        tracer.deallocate_any_that(|p| *p != head);
+        tracer.allocate_next(HEAP_SIZE - 6, allocator).unwrap();
+        assert_eq!(allocator.num_allocated_blocks(), 4);
+        assert!(allocator.allocated_block_ptr(garbage.block_num()).is_none());
+        let middle = decode_pointer(allocator.load(head).unwrap()).unwrap();
+        let tail = decode_pointer(allocator.load(middle).unwrap()).unwrap();
+        assert_eq!(allocator.load(tail).unwrap(), 17);
+        allocator.assert_no_strays();
+    }
+
+    #[test]
+    fn scan_order_test() {
+        for (scan_order, expected) in [
+            (ScanOrder::BreadthFirst, ["root", "a", "b", "c", "d"]),
+            (ScanOrder::DepthFirst, ["root", "a", "c", "d", "b"]),
+        ] {
+            let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+            allocator.set_scan_order(scan_order);
+            let mut tracer = TestTracer::default();
+            let mut nodes = HashMap::new();
+            for name in ["garbage", "d", "c", "b", "a", "root"] {
+                nodes.insert(name, tracer.allocate_next(2, &mut allocator).unwrap());
+            }
+            for (parent, children) in [("root", ["a", "b"]), ("a", ["c", "d"]), ("b", ["d", "c"])] {
+                for (pt, child) in nodes[parent].iter().zip(children) {
+                    allocator.store(pt, encode_pointer(nodes[child])).unwrap();
+                }
+            }
+
+            tracer.deallocate_any_that(|p| *p != nodes["root"]);
+            tracer.allocate_next(HEAP_SIZE - 11, &mut allocator).unwrap();
+            for (i, name) in expected.iter().enumerate() {
+                assert_eq!(allocator.address(nodes[name]).unwrap(), i * 2);
+            }
+            assert!(allocator.allocated_block_ptr(nodes["garbage"].block_num()).is_none());
+            allocator.assert_no_strays();
+        }
+    }
+
+    #[test]
+    fn explicit_collect_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        let summary = allocator.collect(&tracer).unwrap();
+        assert_eq!(summary, CollectionSummary {
+            blocks_reclaimed: 6,
+            words_reclaimed: 70,
+            blocks_live: 6,
+            words_live: 24,
+        });
+        allocator.assert_no_strays();
+        test_load_store(&blocks2ptrs, &mut allocator);
+        assert_eq!(allocator.collect(&tracer).unwrap().blocks_reclaimed, 0);
+
+        // Every heap reports the same summary from either kind of collection.
+        test_collect_half::<MarkSweepHeap<HEAP_SIZE, MAX_BLOCKS>>();
+        test_collect_half::<MarkCompactHeap<HEAP_SIZE, MAX_BLOCKS>>();
+        test_collect_half::<IncrementalCopyingHeap<HEAP_SIZE, MAX_BLOCKS>>();
+        test_collect_half::<GenerationalHeap<HEAP_SIZE, MAX_BLOCKS, 2>>();
+        test_collect_half::<MultiGenerationalHeap<HEAP_SIZE, MAX_BLOCKS, 3>>();
+    }
+
+    fn test_collect_half<H: CollectableHeap + GarbageCollectingHeap>() {
+        for minor in [false, true] {
+            let mut blocks2ptrs = HashMap::new();
+            let mut allocator = H::new();
+            let mut tracer = TestTracer::default();
+            test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+            test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+            let summary = if minor {
+                allocator.collect_minor(&tracer).unwrap()
+            } else {
+                allocator.collect(&tracer).unwrap()
+            };
+            assert_eq!(summary.words_reclaimed, 70);
+            assert_eq!(summary.blocks_live, 6);
+            test_load_store(&blocks2ptrs, &mut allocator);
+        }
+    }
+
+    #[test]
+    fn explicit_generational_collect_test() {
+        let mut allocator = GenerationalHeap::<100, 120, 2>::new();
+        let mut tracer = TestTracer::default();
+        let mut blocks2ptrs = HashMap::new();
+        allocate_many(12, &mut allocator, &mut tracer, &mut blocks2ptrs);
+        for _ in 0..3 {
+            allocator.collect_minor(&tracer).unwrap();
+        }
+        for (_, c) in allocator.blocks_num_copies() {
+            assert_eq!(c, 3);
+        }
+
+        // A minor collection does not trace generation 1, so only a full one reclaims it.
+        tracer.deallocate_any_that(|p| p.len() == 1);
+        let summary = allocator.collect_minor(&tracer).unwrap();
+        assert_eq!((summary.blocks_reclaimed, summary.words_reclaimed), (0, 0));
+        for (_, c) in allocator.blocks_num_copies() {
+            assert_eq!(c, 3);
+        }
+
+        let summary = allocator.collect(&tracer).unwrap();
+        assert_eq!((summary.blocks_reclaimed, summary.words_reclaimed), (3, 3));
+        assert_eq!((summary.blocks_live, summary.words_live), (9, 27));
+        for (_, c) in allocator.blocks_num_copies() {
+            assert_eq!(c, 4);
+        }
+        allocator.assert_no_strays();
+    }
+
+    #[test]
+    fn remembered_set_test() {
+        let mut allocator = GenerationalHeap::<HEAP_SIZE, MAX_BLOCKS, 1>::new();
+        let mut tracer = TestTracer::default();
+        let old = tracer.allocate_next(2, &mut allocator).unwrap();
+        allocator.collect_minor(&tracer).unwrap();
+        allocator.collect_minor(&tracer).unwrap();
+        assert!(allocator.in_gen_1(old.block_num()));
+
+        // Reachable only through a generation 1 block, which the write barrier remembers.
+        let young = tracer.allocate_next(2, &mut allocator).unwrap();
+        allocator.store(young, 7).unwrap();
+        allocator.store(old, encode_pointer(young)).unwrap();
+        tracer.deallocate_any_that(|p| *p == young);
+        allocator.collect_minor(&tracer).unwrap();
+        assert_eq!(allocator.load(young), Ok(7));
+        assert!(allocator.block_info.collector(old.block_num()).remembered);
+
+        // Once promoted, `young` no longer needs `old` remembered.
+        allocator.collect_minor(&tracer).unwrap();
+        assert!(allocator.in_gen_1(young.block_num()));
+        assert!(!allocator.block_info.collector(old.block_num()).remembered);
+
+        // Minor collections keep unreachable generation 1 blocks; a full one frees them.
+        tracer.deallocate_any_that(|p| *p == old);
+        assert_eq!(allocator.collect_minor(&tracer).unwrap().blocks_live, 2);
+        assert_eq!(allocator.collect(&tracer).unwrap().blocks_live, 0);
+        allocator.assert_no_strays();
+    }
+
+    #[test]
+    fn card_marking_test() {
+        let mut allocator =
+            GenerationalHeap::<HEAP_SIZE, MAX_BLOCKS, 1, NoObserver, CollectOnFailure, 4, 24>::new();
+        let mut tracer = TestTracer::default();
+        let old = tracer.allocate_next(8, &mut allocator).unwrap();
+        allocator.collect_minor(&tracer).unwrap();
+        allocator.collect_minor(&tracer).unwrap();
+
+        // The sixth word of `old` is on its second card.
+        let young = tracer.allocate_next(2, &mut allocator).unwrap();
+        allocator.store(young, 7).unwrap();
+        allocator.store(old.iter().nth(5).unwrap(), encode_pointer(young)).unwrap();
+        assert_eq!(allocator.cards[..2], [0, 1]);
+        tracer.deallocate_any_that(|p| *p == young);
+        allocator.collect_minor(&tracer).unwrap();
+        assert_eq!(allocator.load(young), Ok(7));
+        assert_eq!(allocator.stats().last().unwrap().cards_scanned, 1);
+
+        // Promoting `young` leaves the card clean, so the next minor collection scans none.
+        allocator.collect_minor(&tracer).unwrap();
+        assert!(allocator.in_gen_1(young.block_num()));
+        assert_eq!(allocator.cards[1], 0);
+        allocator.collect_minor(&tracer).unwrap();
+        assert_eq!(allocator.stats().last().unwrap().cards_scanned, 0);
+        assert_eq!(allocator.stats().totals().cards_scanned, 2);
+        assert!(allocator.verify().is_empty());
+
+        // A minor collection whose promotions do not fit becomes a full one, which scans no cards.
+        let mut allocator = GenerationalHeap::<16, MAX_BLOCKS, 0, NoObserver, CollectOnFailure, 4, 4>::new();
+        let mut tracer = TestTracer::default();
+        let old = tracer.allocate_next(8, &mut allocator).unwrap();
+        allocator.collect_minor(&tracer).unwrap();
+        let young = tracer.allocate_next(2, &mut allocator).unwrap();
+        tracer.allocate_next(8, &mut allocator).unwrap();
+        allocator.store(old.iter().nth(5).unwrap(), encode_pointer(young)).unwrap();
+        tracer.deallocate_any_that(|p| *p == old);
+        allocator.collect_minor(&tracer).unwrap();
+        let last = allocator.stats().last().unwrap();
+        assert_eq!((last.gen_1_collected, last.cards_scanned), (true, 0));
+
+        // When CARD_SIZE does not divide the generation's size, the last card is partial.
+        let mut allocator = GenerationalHeap::<18, MAX_BLOCKS, 1, NoObserver, CollectOnFailure, 4, 5>::new();
+        let mut tracer = TestTracer::default();
+        tracer.allocate_next(16, &mut allocator).unwrap();
+        let old = tracer.allocate_next(2, &mut allocator).unwrap();
+        allocator.collect_minor(&tracer).unwrap();
+        allocator.collect_minor(&tracer).unwrap();
+        assert_eq!(allocator.address(old), Ok(16));
+        let young = tracer.allocate_next(2, &mut allocator).unwrap();
+        allocator.store(young, 7).unwrap();
+        allocator.store(old.iter().nth(1).unwrap(), encode_pointer(young)).unwrap();
+        assert_eq!(allocator.cards, [0, 0, 0, 0, 1]);
+        tracer.deallocate_any_that(|p| *p == young);
+        allocator.collect_minor(&tracer).unwrap();
+        assert!(!allocator.in_gen_1(young.block_num()));
+        assert_eq!(allocator.load(young), Ok(7));
+        assert_eq!(allocator.stats().last().unwrap().cards_scanned, 1);
+        assert!(allocator.verify().is_empty());
+    }
+
+    #[test]
+    fn nursery_size_test() {
+        let mut allocator = GenerationalHeap::<16, MAX_BLOCKS, 0, NoObserver, CollectOnFailure, 0, 0, 64>::new();
+        let mut tracer = TestTracer::default();
+        let mut blocks2ptrs = HashMap::new();
+        allocate_many(10, &mut allocator, &mut tracer, &mut blocks2ptrs);
+        assert_eq!(total_words_allocated(&blocks2ptrs), 23);
+        assert!(allocator.stats().totals().promotions > 0);
+        ensure_all_match(&blocks2ptrs, &allocator);
+        for p in blocks2ptrs.values() {
+            assert_eq!(allocator.load(*p).unwrap(), p.len() as u64);
+        }
+
+        // However much room generation 1 has, a block must first fit in the nursery.
+        assert_eq!(tracer.allocate_next(17, &mut allocator), Err(HeapError::OutOfMemory));
+        assert!(allocator.verify().is_empty());
+        allocator.assert_no_strays();
     }
 
-    fn assert_no_strays(&self) {
-        assert!(self.gen_0[(self.active_gen_0 + 1) % 2].next_address == 0);
-        assert!(self.gen_1[(self.active_gen_1 + 1) % 2].next_address == 0);
+    #[test]
+    fn multi_generational_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = MultiGenerationalHeap::<HEAP_SIZE, MAX_BLOCKS, 3>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_out_of_blocks(&mut allocator, &mut tracer);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_force_collection(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        allocator.assert_no_strays();
+        test_load_store(&blocks2ptrs, &mut allocator);
+        assert!(allocator.verify().is_empty());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::collections::{HashMap, HashSet};
-    use core::fmt::Debug;
+    #[test]
+    fn multi_generational_overflow_test() {
+        let mut allocator = MultiGenerationalHeap::<8, MAX_BLOCKS, 3>::new();
+        for generation in 0..3 {
+            allocator.set_max_copies(generation, 0).unwrap();
+        }
+        let mut tracer = TestTracer::default();
+        let mut ptrs = vec![];
+        for i in 0..3 {
+            let p = tracer.allocate_next(4, &mut allocator).unwrap();
+            allocator.store(p, i).unwrap();
+            allocator.collect_generation(&tracer, 0).unwrap();
+            ptrs.push(p);
+        }
 
-    use super::*;
-    use test_tracer::TestTracer;
+        // Promoting the third block would overflow generation 1, so generation 1 was
+        // collected too, promoting the first two blocks into generation 2.
+        let generations = ptrs.iter().map(|p| allocator.generation_of(p.block_num()).unwrap());
+        assert_eq!(generations.collect::<Vec<_>>(), vec![2, 2, 1]);
+        let records = allocator.stats().records().map(|r| r.gen_1_collected).collect::<Vec<_>>();
+        assert_eq!(records, vec![false, false, true]);
+        for (i, p) in ptrs.iter().enumerate() {
+            assert_eq!(allocator.load(*p), Ok(i as u64));
+        }
 
-    const HEAP_SIZE: usize = 96;
-    const MAX_BLOCKS: usize = 12;
+        // There is no room for the third block in generation 2 until the second is dropped.
+        assert_eq!(allocator.collect(&tracer), Err(HeapError::OutOfMemory));
+        tracer.deallocate_any_that(|p| *p == ptrs[1]);
+        allocator.collect(&tracer).unwrap();
+        assert_eq!(allocator.generation_of(ptrs[2].block_num()), Some(2));
 
-    // Level 1 Unit Tests
+        // The oldest generation keeps its blocks however often they are copied.
+        allocator.collect(&tracer).unwrap();
+        assert_eq!(allocator.generation_of(ptrs[0].block_num()), Some(2));
+        assert_eq!(allocator.load(ptrs[2]), Ok(2));
+        assert!(allocator.verify().is_empty());
+    }
 
     #[test]
-    fn block_table_test() {
-        let mut table = BlockTable::<5>::new();
-        assert_eq!(table.available_block().unwrap(), 0);
-        table[0] = Some(BlockInfo { start: 3, size: 2, num_times_copied: 0 });
-        assert_eq!(table.available_block().unwrap(), 1);
-        table[2] = Some(BlockInfo { start: 5, size: 3, num_times_copied: 0 });
-        assert_eq!(table.available_block().unwrap(), 1);
-        table[1] = Some(BlockInfo { start: 8, size: 2, num_times_copied: 0 });
-        assert_eq!(table.available_block().unwrap(), 3);
+    fn multi_generational_barrier_test() {
+        let mut allocator = MultiGenerationalHeap::<HEAP_SIZE, MAX_BLOCKS, 3, CountingObserver>::new();
+        let mut tracer = TestTracer::default();
+        assert_eq!(allocator.set_max_copies(3, 0), Err(GenerationError::NoSuchGeneration(3, 3)));
+        assert_eq!(allocator.collect_generation(&tracer, 3), Err(GenerationError::NoSuchGeneration(3, 3)));
+        allocator.set_max_copies(0, 0).unwrap();
+        let old = tracer.allocate_next(2, &mut allocator).unwrap();
+        allocator.collect_generation(&tracer, 0).unwrap();
+        assert_eq!(allocator.generation_of(old.block_num()), Some(1));
 
-        let p = Pointer::new(0, 2);
-        for (i, ptr) in p.iter().enumerate() {
-            assert_eq!(table.address(ptr).unwrap(), i + 3);
+        // A block referred to only from an older generation survives a minor collection,
+        // after which the two are in the same generation and nothing need be remembered.
+        let young = tracer.allocate_next(1, &mut allocator).unwrap();
+        allocator.store(young, 17).unwrap();
+        allocator.store(old, encode_pointer(young)).unwrap();
+        tracer.deallocate_any_that(|p| *p == young);
+        assert!(allocator.block_info.collector(old.block_num()).remembered);
+        allocator.collect_generation(&tracer, 0).unwrap();
+        assert_eq!(allocator.generation_of(young.block_num()), Some(1));
+        assert_eq!(allocator.load(young), Ok(17));
+        assert!(!allocator.block_info.collector(old.block_num()).remembered);
+
+        // A minor collection keeps every block of the older generations, reachable or not.
+        tracer.deallocate_any_that(|p| *p == old);
+        allocator.collect_generation(&tracer, 0).unwrap();
+        assert_eq!(allocator.num_allocated_blocks(), 2);
+        allocator.collect(&tracer).unwrap();
+        assert_eq!(allocator.num_allocated_blocks(), 0);
+        let observer = allocator.observer();
+        assert_eq!((observer.mallocs, observer.collections), (2, 4));
+        assert_eq!((observer.promoted, observer.copied, observer.freed), (2, 0, 2));
+        assert!(allocator.verify().is_empty());
+    }
+
+    #[test]
+    fn adaptive_tenuring_test() {
+        let mut allocator = GenerationalHeap::<HEAP_SIZE, MAX_BLOCKS, 3>::new();
+        allocator.set_adaptive_tenuring(true);
+        let mut tracer = TestTracer::default();
+        let mut ptrs = vec![];
+        for _ in 0..6 {
+            ptrs.push(tracer.allocate_next(10, &mut allocator).unwrap());
         }
-        let end_ptr = p.iter().last().unwrap();
-        table[0] = Some(BlockInfo {start: 3, size: 1, num_times_copied: 0});
-        assert_eq!(table.address(p), Err(HeapError::MisalignedPointer(2, 1, 0)));
-        assert_eq!(table.address(end_ptr), Err(HeapError::OffsetTooBig(1, 0, 1)));
 
-        let p = Pointer::new(5, 2);
-        assert_eq!(table.address(p), Err(HeapError::IllegalBlock(5, 4)));
+        // Sixty words of survivors fill more than half of generation 0, so they are all
+        // promoted by the next minor collection instead of waiting for three copies.
+        allocator.collect_minor(&tracer).unwrap();
+        assert_eq!(allocator.tenuring_threshold(), 1);
+        allocator.collect_minor(&tracer).unwrap();
+        assert!(ptrs.iter().all(|p| allocator.in_gen_1(p.block_num())));
+        assert_eq!(allocator.stats().last().unwrap().promotions, 6);
 
-        let p = Pointer::new(3, 2);
-        assert_eq!(table.address(p), Err(HeapError::UnallocatedBlock(3)));
+        // With generation 0 empty again, the threshold returns to its upper bound.
+        assert_eq!(allocator.tenuring_threshold(), 3);
+        let young = tracer.allocate_next(10, &mut allocator).unwrap();
+        allocator.collect_minor(&tracer).unwrap();
+        allocator.collect_minor(&tracer).unwrap();
+        assert!(!allocator.in_gen_1(young.block_num()));
+        assert!(allocator.verify().is_empty());
     }
 
     #[test]
-    fn basic_allocation_test() {
+    fn copying_stats_test() {
         let mut blocks2ptrs = HashMap::new();
-        let mut allocator = OnceAndDoneHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
         let mut tracer = TestTracer::default();
         test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_out_of_blocks(&mut allocator, &mut tracer);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_force_collection(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_fill_ram(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_out_of_ram(&mut allocator, &mut tracer);
+
+        let records = allocator.stats().records().collect::<Vec<_>>();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].trigger, CollectionTrigger::OutOfBlocks);
+        assert_eq!((records[0].words_copied, records[0].words_reclaimed), (94, 0));
+        assert_eq!(records[1].trigger, CollectionTrigger::OutOfBlocks);
+        assert_eq!((records[1].words_copied, records[1].words_reclaimed), (24, 70));
+        assert_eq!(records[2].trigger, CollectionTrigger::OutOfMemory);
+        assert_eq!((records[2].blocks_live, records[2].words_copied), (8, 96));
+        let totals = allocator.stats().totals();
+        assert_eq!((totals.collections, totals.words_copied, totals.words_reclaimed), (3, 214, 70));
     }
 
     #[test]
-    fn out_of_blocks_test() {
+    fn generational_stats_test() {
+        let mut allocator = GenerationalHeap::<100, 120, 2>::new();
+        let mut tracer = TestTracer::default();
         let mut blocks2ptrs = HashMap::new();
-        let mut allocator = OnceAndDoneHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        allocate_many(40, &mut allocator, &mut tracer, &mut blocks2ptrs);
+        for expected_copies in 1..=3 {
+            force_copy_n(expected_copies, &mut allocator, &mut tracer, &mut blocks2ptrs);
+        }
+        let last = allocator.stats().last().unwrap();
+        assert_eq!(last.trigger, CollectionTrigger::OutOfMemory);
+        assert_eq!((last.promotions, last.gen_1_collected), (37, false));
+
+        allocator.collect(&tracer).unwrap();
+        let last = allocator.stats().last().unwrap();
+        assert_eq!(last.trigger, CollectionTrigger::Explicit);
+        assert!(last.gen_1_collected);
+        let totals = allocator.stats().totals();
+        assert_eq!((totals.collections, totals.promotions, totals.gen_1_collections), (4, 38, 1));
+    }
+
+    #[derive(Default, Debug)]
+    struct CountingObserver {
+        mallocs: usize,
+        collections: usize,
+        copied: usize,
+        freed: usize,
+        promoted: usize,
+        words_reclaimed: usize,
+    }
+
+    impl HeapObserver for CountingObserver {
+        fn on_malloc(&mut self, _block: usize, _num_words: usize) {
+            self.mallocs += 1;
+        }
+
+        fn on_collect_start(&mut self) {
+            self.collections += 1;
+        }
+
+        fn on_block_copied(&mut self, _block: usize, _num_words: usize) {
+            self.copied += 1;
+        }
+
+        fn on_block_freed(&mut self, _block: usize, _num_words: usize) {
+            self.freed += 1;
+        }
+
+        fn on_promote(&mut self, _block: usize, _num_words: usize) {
+            self.promoted += 1;
+        }
+
+        fn on_collect_end(&mut self, summary: CollectionSummary) {
+            self.words_reclaimed += summary.words_reclaimed;
+        }
+    }
+
+    #[test]
+    fn observer_test() {
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS, CountingObserver>::new();
         let mut tracer = TestTracer::default();
         test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
-        test_out_of_blocks(&mut allocator, &mut tracer);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_force_collection(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        let observer = allocator.observer();
+        assert_eq!((observer.mallocs, observer.collections), (13, 1));
+        assert_eq!((observer.copied, observer.freed, observer.words_reclaimed), (6, 6, 70));
+
+        let mut allocator = GenerationalHeap::<100, 120, 2, CountingObserver>::new();
+        let mut tracer = TestTracer::default();
+        let mut blocks2ptrs = HashMap::new();
+        allocate_many(40, &mut allocator, &mut tracer, &mut blocks2ptrs);
+        for expected_copies in 1..=3 {
+            force_copy_n(expected_copies, &mut allocator, &mut tracer, &mut blocks2ptrs);
+        }
+        let observer = allocator.observer();
+        assert_eq!((observer.mallocs, observer.collections), (43, 3));
+        assert_eq!((observer.freed, observer.promoted), (3, 37));
     }
 
     #[test]
-    fn test_bad_address_error() {
-        let mut allocator = RamHeap::<HEAP_SIZE>::new();
-        match allocator.load(HEAP_SIZE + 1) {
-            Ok(_) => panic!("This should have been an IllegalAddress error."),
-            Err(e) => assert_eq!(e, HeapError::IllegalAddress(HEAP_SIZE + 1, 0))
-        }
+    fn stress_mode_test() {
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        allocator.set_stress_mode(StressMode::CollectAndPoison);
+        test_missing_root(&mut allocator);
+        assert!(allocator.heaps[(allocator.active_heap + 1) % 2].heap.iter().all(|w| *w == POISON));
+        assert!(allocator.stats().records().all(|r| r.trigger == CollectionTrigger::Stress));
 
-        allocator.malloc(96).unwrap();
-        match allocator.load(HEAP_SIZE + 1) {
-            Ok(_) => panic!("This should have been an IllegalAddress error."),
-            Err(e) => assert_eq!(e, HeapError::IllegalAddress(HEAP_SIZE + 1, HEAP_SIZE))
+        let mut allocator = GenerationalHeap::<HEAP_SIZE, MAX_BLOCKS, 2>::new();
+        allocator.set_stress_mode(StressMode::CollectAndPoison);
+        test_missing_root(&mut allocator);
+        let (inactive_0, inactive_1) = ((allocator.active_gen_0 + 1) % 2, (allocator.active_gen_1 + 1) % 2);
+        assert!(allocator.gen_0[inactive_0].heap.iter().all(|w| *w == POISON));
+        assert!(allocator.gen_1[inactive_1].heap.iter().all(|w| *w == POISON));
+
+        let mut allocator = MultiGenerationalHeap::<HEAP_SIZE, MAX_BLOCKS, 3>::new();
+        allocator.set_stress_mode(StressMode::CollectAndPoison);
+        test_missing_root(&mut allocator);
+        for (heaps, active) in allocator.heaps.iter().zip(allocator.active) {
+            assert!(heaps[(active + 1) % 2].heap.iter().all(|w| *w == POISON));
         }
     }
 
-    // Level 2 Unit Tests
+    #[test]
+    fn trigger_policy_test() {
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS, NoObserver, AllocationCountTrigger>::new();
+        allocator.set_trigger_policy(AllocationCountTrigger::new(3));
+        let mut tracer = TestTracer::default();
+        for _ in 0..7 {
+            tracer.allocate_next(1, &mut allocator).unwrap();
+        }
+        assert_eq!(allocator.stats().totals().collections, 2);
+        assert!(allocator.stats().records().all(|r| r.trigger == CollectionTrigger::Policy));
 
+        let mut allocator = MultiGenerationalHeap::<HEAP_SIZE, MAX_BLOCKS, 3, NoObserver, AllocationCountTrigger>::new();
+        allocator.set_trigger_policy(AllocationCountTrigger::new(3));
+        let mut tracer = TestTracer::default();
+        for _ in 0..7 {
+            tracer.allocate_next(1, &mut allocator).unwrap();
+        }
+        assert_eq!(allocator.stats().totals().collections, 2);
+        assert!(allocator.stats().records().all(|r| r.trigger == CollectionTrigger::Policy));
+
+        // The tenth request would leave generation 0 and the block table more than 75% full.
+        let mut allocator = GenerationalHeap::<HEAP_SIZE, MAX_BLOCKS, 2, NoObserver, OccupancyTrigger>::new();
+        let mut tracer = TestTracer::default();
+        for _ in 0..9 {
+            tracer.allocate_next(8, &mut allocator).unwrap();
+        }
+        assert_eq!(allocator.stats().totals().collections, 0);
+        tracer.allocate_next(8, &mut allocator).unwrap();
+        let last = allocator.stats().last().unwrap();
+        assert_eq!((last.trigger, last.gen_1_collected), (CollectionTrigger::Policy, false));
+
+        // The early collection cannot promote the live block into generation 1, but the
+        // request still fits in generation 0.
+        let mut allocator = GenerationalHeap::<32, MAX_BLOCKS, 0, NoObserver, OccupancyTrigger, 0, 0, 8>::new();
+        allocator.set_trigger_policy(OccupancyTrigger { percent: 50 });
+        let mut tracer = TestTracer::default();
+        tracer.allocate_next(10, &mut allocator).unwrap();
+        tracer.allocate_next(8, &mut allocator).unwrap();
+        assert_eq!(allocator.stats().totals().collections, 0);
+        assert_eq!(allocator.num_allocated_blocks(), 2);
+        assert!(allocator.verify().is_empty());
+    }
+
+    #[cfg(feature = "poison")]
     #[test]
-    fn deallocation_test() {
-        let mut blocks2ptrs = HashMap::new();
+    fn poison_feature_test() {
         let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
         let mut tracer = TestTracer::default();
-        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
-        allocator.assert_no_strays();
-        test_out_of_blocks(&mut allocator, &mut tracer);
-        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        let p = tracer.allocate_next(2, &mut allocator).unwrap();
+        let second = p.iter().nth(1).unwrap();
+        assert_eq!(allocator.poison_checked_load(second), Ok(0));
+        allocator.store(p, 4).unwrap();
+
+        // Collection poisons the semispace it vacates.
+        allocator.collect(&tracer).unwrap();
+        assert!(allocator.heaps[(allocator.active_heap + 1) % 2].heap.iter().all(|w| *w == POISON));
+        assert_eq!(allocator.poison_checked_load(p), Ok(4));
+        assert_eq!(allocator.poison_checked_load(second), Ok(0));
+
+        // A block table entry left pointing at words that were never allocated reads poison,
+        // which only the checked load reports.
+        let info = allocator.block_info[p.block_num()].unwrap();
+        allocator.block_info.set(p.block_num(), Some(BlockInfo { start: HEAP_SIZE - 2, ..info }));
+        allocator.heaps[allocator.active_heap].next_address = HEAP_SIZE;
+        assert_eq!(allocator.load(second), Ok(POISON));
+        assert_eq!(allocator.poison_checked_load(second), Err(LoadError::Poisoned(HEAP_SIZE - 1)));
+        assert_eq!(
+            allocator.poison_checked_load(Pointer::new(p.block_num(), 3)),
+            Err(LoadError::Heap(HeapError::MisalignedPointer(3, 2, p.block_num())))
+        );
+
+        // The buddy allocator poisons the chunks it frees.
+        let mut buddy = BuddyRamHeap::<16>::new();
+        let start = buddy.malloc(4).unwrap();
+        assert_eq!(buddy.checked_load(start + 1), Ok(0));
+        buddy.free(start, 4);
+        assert_eq!(buddy.checked_load(start + 1), Err(LoadError::Poisoned(start + 1)));
+    }
+
+    fn test_missing_root<H: GarbageCollectingHeap>(allocator: &mut H) {
+        let mut tracer = TestTracer::default();
+        let rooted = tracer.allocate_next(2, allocator).unwrap();
+        allocator.store(rooted, 5).unwrap();
+
+        // A block the tracer forgets about is reclaimed by the very next malloc.
+        let forgotten = allocator.malloc(3, &tracer).unwrap();
+        allocator.store(forgotten, 6).unwrap();
+        let p = tracer.allocate_next(1, allocator).unwrap();
+        assert_eq!(p.block_num(), forgotten.block_num());
+        assert_eq!(allocator.num_allocated_blocks(), 2);
+        assert!(allocator.load(forgotten).is_err());
+        assert_eq!(allocator.load(rooted).unwrap(), 5);
     }
 
     #[test]
-    fn collection_test() {
+    fn verify_test() {
         let mut blocks2ptrs = HashMap::new();
         let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
         let mut tracer = TestTracer::default();
         test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
-        test_out_of_blocks(&mut allocator, &mut tracer);
         test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
         test_force_collection(&mut allocator, &mut tracer, &mut blocks2ptrs);
-        allocator.assert_no_strays();
+        assert!(allocator.verify().is_empty());
+        allocator.heaps[(allocator.active_heap + 1) % 2].next_address = 3;
+        assert_eq!(allocator.verify().iter().collect::<Vec<_>>(), vec![Violation::Strays(3)]);
+
+        let mut allocator = GenerationalHeap::<100, 120, 2>::new();
+        let mut tracer = TestTracer::default();
+        let mut blocks2ptrs = HashMap::new();
+        allocate_many(40, &mut allocator, &mut tracer, &mut blocks2ptrs);
+        for expected_copies in 1..=3 {
+            force_copy_n(expected_copies, &mut allocator, &mut tracer, &mut blocks2ptrs);
+            assert!(allocator.verify().is_empty());
+        }
+
+        // A promoted block copied no more than the threshold, or a young one copied too often,
+        // is in the wrong generation.
+        let block = allocator.block_info.blocks_in_use().find(|b| allocator.block_info.collector(*b).tenured).unwrap();
+        let info = allocator.block_info[block].unwrap();
+        allocator.block_info.set(block, Some(BlockInfo { num_times_copied: 2, ..info }));
+        assert_eq!(
+            allocator.verify().iter().collect::<Vec<_>>(),
+            vec![Violation::Generation(block, 2, 1)]
+        );
+        allocator.block_info.set(block, Some(info));
+        allocator.block_info.collector_mut(block).tenured = false;
+        assert!(allocator.verify().iter().any(|v| v == Violation::Generation(block, 3, 0)));
+
+        let mut blocks2ptrs = HashMap::new();
+        let mut allocator = MarkSweepHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        assert!(allocator.verify().is_empty());
+
+        // Moving block 2 onto block 1 is caught, as is copying it in a non-moving heap.
+        allocator.block_info.set(2, Some(BlockInfo { start: 2, size: 4, num_times_copied: 1 }));
+        assert_eq!(
+            allocator.verify().iter().collect::<Vec<_>>(),
+            vec![Violation::Overlap(1, 2), Violation::CopyCount(2, 1, 0)]
+        );
     }
 
     #[test]
-    fn full_test() {
+    fn failed_collection_test() {
         let mut blocks2ptrs = HashMap::new();
-        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS, CountingObserver>::new();
         let mut tracer = TestTracer::default();
         test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
         test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
-        test_force_collection(&mut allocator, &mut tracer, &mut blocks2ptrs);
-        test_fill_ram(&mut allocator, &mut tracer, &mut blocks2ptrs);
-        allocator.assert_no_strays();
-        test_out_of_ram(&mut allocator, &mut tracer);
+
+        // Stray words in to-space leave too little room for the survivors, so the collection
+        // fails without changing anything or telling the observer.
+        let inactive = (allocator.active_heap + 1) % 2;
+        allocator.heaps[inactive].next_address = HEAP_SIZE - 10;
+        assert_eq!(allocator.collect(&tracer), Err(HeapError::OutOfMemory));
+        assert_eq!(allocator.verify().iter().collect::<Vec<_>>(), vec![Violation::Strays(HEAP_SIZE - 10)]);
+        assert_eq!(allocator.num_allocated_blocks(), 12);
+        test_load_store(&blocks2ptrs, &mut allocator);
+        assert_eq!((allocator.observer().collections, allocator.observer().copied), (0, 0));
+        allocator.heaps[inactive].next_address = 0;
+        assert_eq!(allocator.collect(&tracer).unwrap().blocks_live, 6);
+
+        // With no copies allowed in generation 0, every survivor is promoted, and the
+        // second batch does not fit in generation 1 alongside the first.
+        let mut allocator = GenerationalHeap::<10, MAX_BLOCKS, 0, CountingObserver>::new();
+        let mut tracer = TestTracer::default();
+        let mut blocks2ptrs = HashMap::new();
+        allocate_many(4, &mut allocator, &mut tracer, &mut blocks2ptrs);
+        allocator.collect_minor(&tracer).unwrap();
+        allocate_many(4, &mut allocator, &mut tracer, &mut blocks2ptrs);
+        assert_eq!(allocator.collect_minor(&tracer), Err(HeapError::OutOfMemory));
+        assert_eq!((allocator.observer().collections, allocator.observer().promoted), (1, 4));
+        assert!(allocator.verify().is_empty());
+        assert_eq!(allocator.num_allocated_blocks(), 8);
+        ensure_all_match(&blocks2ptrs, &allocator);
+        for p in blocks2ptrs.values() {
+            assert_eq!(allocator.load(*p).unwrap(), p.len() as u64);
+        }
     }
 
     #[test]
-    fn test_no_blocks_error() {
+    fn incremental_copying_test() {
         let mut blocks2ptrs = HashMap::new();
-        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut allocator = IncrementalCopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
         let mut tracer = TestTracer::default();
         test_initial_allocation(&mut allocator, &mut tracer, &mut blocks2ptrs);
         test_out_of_blocks(&mut allocator, &mut tracer);
+        test_remove_half(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_force_collection(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        allocator.assert_no_strays();
+        test_load_store(&blocks2ptrs, &mut allocator);
+        test_fill_ram(&mut allocator, &mut tracer, &mut blocks2ptrs);
+        test_out_of_ram(&mut allocator, &mut tracer);
+        assert!(allocator.verify().is_empty());
     }
 
     #[test]
-    fn test_zero_size_error() {
-        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
-        let tracer = TestTracer::default();
-        match allocator.malloc(0, &tracer) {
-            Ok(_) => panic!("This should have been a zero-size error"),
-            Err(e) => assert_eq!(e, HeapError::ZeroSizeRequest),
+    fn incremental_barrier_test() {
+        let mut allocator = IncrementalCopyingHeap::<HEAP_SIZE, MAX_BLOCKS, OccupancyTrigger>::new();
+        allocator.set_trigger_policy(OccupancyTrigger { percent: 50 });
+        allocator.set_work_per_malloc(8);
+        let mut tracer = TestTracer::default();
+        let mut nodes = vec![];
+        for i in 0..5 {
+            let p = tracer.allocate_next(4, &mut allocator).unwrap();
+            for pt in p.iter() {
+                allocator.store(pt, 100 + i).unwrap();
+            }
+            nodes.push(p);
+        }
+        let (a, b, c, d, e) = (nodes[0], nodes[1], nodes[2], nodes[3], nodes[4]);
+        for (from, offset, to) in [(a, 0, b), (b, 0, c), (c, 0, d), (c, 1, e)] {
+            allocator.store(from.iter().nth(offset).unwrap(), encode_pointer(to)).unwrap();
+        }
+        tracer.allocate_next(1, &mut allocator).unwrap();
+        tracer.deallocate_any_that(|p| *p != a);
+
+        // The seventh block would take the table past half full, so the collection starts,
+        // copying the root and, within this malloc's budget, the block it refers to.
+        tracer.allocate_next(1, &mut allocator).unwrap();
+        assert!(allocator.is_collecting());
+        assert!(allocator.verify().is_empty());
+
+        // Reading the only reference to `e` out of `c`, which is still in from-space,
+        // moves both to to-space, so the mutator never picks up a from-space reference.
+        assert!(![c, d, e].iter().any(|p| evacuated(&allocator, *p)));
+        let link = allocator.load(c.iter().nth(1).unwrap()).unwrap();
+        assert_eq!(decode_pointer(link), Some(e));
+        assert!(evacuated(&allocator, c) && evacuated(&allocator, e));
+
+        // Move it into `a`, which has already been scanned, along with a reference to
+        // `d`, which the store moves out of from-space.
+        allocator.store(a, link).unwrap();
+        allocator.store(c.iter().nth(1).unwrap(), 0).unwrap();
+        assert!(!evacuated(&allocator, d));
+        allocator.store(a.iter().nth(1).unwrap(), encode_pointer(d)).unwrap();
+        assert!(evacuated(&allocator, d));
+
+        let mut mallocs = 0;
+        while allocator.is_collecting() {
+            tracer.allocate_next(1, &mut allocator).unwrap();
+            assert!(allocator.verify().is_empty());
+            mallocs += 1;
         }
+        assert!(mallocs > 1);
+        allocator.assert_no_strays();
+        for (i, p) in [d, e].iter().enumerate() {
+            assert!(p.iter().all(|pt| allocator.load(pt) == Ok(103 + i as u64)));
+        }
+        // Only the unreachable one-word block was left behind in from-space.
+        let record = allocator.stats().last().unwrap();
+        assert_eq!((record.trigger, record.words_reclaimed), (CollectionTrigger::Policy, 1));
     }
 
     #[test]
-    fn test_illegal_block_error() {
-        let allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
-        let bad_ptr = Pointer::new(MAX_BLOCKS, 1);
-        match allocator.load(bad_ptr) {
-            Ok(_) => panic!("This should have been an error"),
-            Err(e) => assert_eq!(e, HeapError::IllegalBlock(MAX_BLOCKS, MAX_BLOCKS - 1))
-        }
+    fn incremental_read_barrier_test() {
+        let mut allocator = IncrementalCopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
+        let mut tracer = TestTracer::default();
+        tracer.allocate_next(1, &mut allocator).unwrap();
+        let a = tracer.allocate_next(1, &mut allocator).unwrap();
+        let b = tracer.allocate_next(2, &mut allocator).unwrap();
+        let c = tracer.allocate_next(1, &mut allocator).unwrap();
+        allocator.store(a, encode_pointer(b)).unwrap();
+        allocator.store(b, encode_pointer(c)).unwrap();
+        allocator.store(b.iter().last().unwrap(), 17).unwrap();
+        tracer.deallocate_any_that(|p| *p != a);
+
+        // Starting the collection evacuates the root `a` alone, leaving the unreachable
+        // first block where `a` goes in to-space.
+        allocator.start(&tracer, CollectionTrigger::Explicit);
+        assert!(evacuated(&allocator, a) && !evacuated(&allocator, b) && !evacuated(&allocator, c));
+        assert_eq!(allocator.address(b), Ok(2));
+
+        // Reading `b` moves it to to-space first, and reading its reference to `c` moves
+        // `c` as well.
+        assert_eq!(allocator.load(b.iter().last().unwrap()), Ok(17));
+        assert!(evacuated(&allocator, b) && !evacuated(&allocator, c));
+        assert_eq!(allocator.address(b), Ok(1));
+        assert_eq!(allocator.load(b), Ok(encode_pointer(c)));
+        assert!(evacuated(&allocator, c));
+        assert_eq!(allocator.address(c), Ok(3));
+        assert!(allocator.verify().is_empty());
+
+        allocator.finish(&tracer);
+        let record = allocator.stats().last().unwrap();
+        assert_eq!((record.words_copied, record.words_reclaimed), (4, 1));
+        assert!(allocator.verify().is_empty());
+    }
+
+    fn evacuated<const HEAP_SIZE: usize, const MAX_BLOCKS: usize, P: TriggerPolicy>(
+        allocator: &IncrementalCopyingHeap<HEAP_SIZE, MAX_BLOCKS, P>,
+        p: Pointer,
+    ) -> bool {
+        let spaces = allocator.spaces.borrow();
+        spaces.heap_of(p.block_num()) == spaces.active_heap
     }
 
     #[test]
-    fn test_unallocated_block_error() {
-        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
-        let tracer = TestTracer::default();
-        let p = allocator.malloc(1, &tracer).unwrap();
-        let bad_ptr = Pointer::new(p.block_num() + 1, 1);
-        match allocator.load(bad_ptr) {
-            Ok(_) => panic!("This should have been an UnallocatedBlock error"),
-            Err(e) => assert_eq!(e, HeapError::UnallocatedBlock(bad_ptr.block_num()))
+    fn incremental_reservation_test() {
+        let mut allocator = IncrementalCopyingHeap::<HEAP_SIZE, MAX_BLOCKS, OccupancyTrigger>::new();
+        allocator.set_trigger_policy(OccupancyTrigger { percent: 50 });
+        allocator.set_work_per_malloc(0);
+        let mut tracer = TestTracer::default();
+        let a = tracer.allocate_next(1, &mut allocator).unwrap();
+        let b = tracer.allocate_next(40, &mut allocator).unwrap();
+        allocator.store(a, encode_pointer(b)).unwrap();
+        allocator.store(b.iter().last().unwrap(), 17).unwrap();
+        tracer.deallocate_any_that(|p| *p == b);
+
+        // The collection starts by evacuating `a` alone, and to-space keeps 40 words
+        // free for `b` for as long as it is still in from-space.
+        tracer.allocate_next(8, &mut allocator).unwrap();
+        tracer.allocate_next(40, &mut allocator).unwrap();
+        assert!(allocator.is_collecting());
+        assert_eq!(tracer.allocate_next(8, &mut allocator), Err(HeapError::OutOfMemory));
+        assert!(!allocator.is_collecting());
+        assert!(allocator.verify().is_empty());
+        let b = decode_pointer(allocator.load(a).unwrap()).unwrap();
+        assert_eq!(allocator.load(b.iter().last().unwrap()), Ok(17));
+    }
+
+    #[derive(Default)]
+    struct Roots(Vec<Pointer>);
+
+    impl Tracer for Roots {
+        fn trace(&self, blocks_used: &mut [bool]) {
+            for p in self.0.iter() {
+                blocks_used[p.block_num()] = true;
+            }
         }
     }
 
     #[test]
-    fn test_offset_error() {
-        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
-        let mut tracer = TestTracer::default();
-        let p = tracer.allocate_next(HEAP_SIZE, &mut allocator).unwrap();
-        let s = p.iter().skip(1).next().unwrap();
-        tracer.deallocate_next().unwrap();
-        tracer.allocate_next(1, &mut allocator).unwrap();
-        let q = tracer.allocate_next(1, &mut allocator).unwrap();
-        assert_eq!(p.block_num(), q.block_num());
-        match allocator.load(s) {
-            Ok(_) => panic!("This should have been an OffsetTooBig error"),
-            Err(e) => assert_eq!(e, HeapError::OffsetTooBig(1, p.block_num(), 1))
+    fn slice_heap_test() {
+        // Sized at run time rather than by const generics. The odd word is left unused.
+        let (num_words, num_blocks) = (4 * HEAP_SIZE + 1, 2 * MAX_BLOCKS);
+        let mut words = vec![0; num_words];
+        let mut blocks = vec![BlockSlot::default(); num_blocks];
+        let mut marks = vec![false; num_blocks];
+
+        let mut allocator: SliceCopyingHeap = SliceCopyingHeap::new(&mut words, &mut blocks, &mut marks);
+        assert_eq!((allocator.heaps[0].size(), allocator.heaps[1].size()), (2 * HEAP_SIZE, 2 * HEAP_SIZE));
+        let mut roots = Roots::default();
+        let tail = allocator.malloc(1, &roots).unwrap();
+        allocator.store(tail, 17).unwrap();
+        let garbage = allocator.malloc(HEAP_SIZE, &roots).unwrap();
+        let head = allocator.malloc(2, &roots).unwrap();
+        allocator.store(head, encode_pointer(tail)).unwrap();
+        roots.0.push(head);
+        let summary = allocator.collect(&roots).unwrap();
+        assert_eq!((summary.blocks_live, summary.words_reclaimed), (2, HEAP_SIZE));
+        assert!(allocator.load(garbage).is_err());
+        assert_eq!(allocator.load(tail).unwrap(), 17);
+
+        // Running out of blocks collects the unrooted ones.
+        for _ in 0..num_blocks {
+            allocator.malloc(1, &roots).unwrap();
         }
+        assert_eq!(allocator.num_allocated_blocks(), 4);
+        // The slice heap keeps the same statistics and checks as the array one.
+        assert_eq!(allocator.stats().totals().collections, 2);
+        assert!(allocator.verify().is_empty());
+
+        // Each kind of heap keeps its own collector state in the block slots.
+        let mut blocks = vec![BlockSlot::default(); num_blocks];
+        let mut allocator: SliceGenerationalHeap<2> = SliceGenerationalHeap::new(&mut words, &mut blocks, &mut marks);
+        let mut roots = Roots::default();
+        let tail = allocator.malloc(1, &roots).unwrap();
+        allocator.store(tail, 17).unwrap();
+        let head = allocator.malloc(2, &roots).unwrap();
+        allocator.store(head, encode_pointer(tail)).unwrap();
+        roots.0.push(head);
+        for _ in 0..3 {
+            allocator.collect_minor(&roots).unwrap();
+        }
+        assert_eq!(allocator.block_info[tail.block_num()].unwrap().num_times_copied, 3);
+        assert_eq!(allocator.load(tail).unwrap(), 17);
+        assert_eq!(allocator.collect(&roots).unwrap().blocks_live, 2);
+        assert_eq!(allocator.load(tail).unwrap(), 17);
+        assert_eq!(allocator.stats().last().unwrap().promotions, 0);
+        assert!(allocator.verify().is_empty());
+
+        let mut blocks = [BlockSlot::default(); 2];
+        let mut allocator: SliceOnceAndDoneHeap = SliceOnceAndDoneHeap::new(&mut words[..4], &mut blocks);
+        let p = allocator.malloc(3, &roots).unwrap();
+        allocator.store(p, 17).unwrap();
+        assert_eq!(allocator.malloc(2, &roots), Err(HeapError::OutOfMemory));
+        allocator.malloc(1, &roots).unwrap();
+        assert_eq!(allocator.malloc(1, &roots), Err(HeapError::OutOfBlocks));
+        assert_eq!(allocator.load(p).unwrap(), 17);
+        assert!(allocator.verify().is_empty());
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
-    fn test_misaligned_pointer_error() {
-        let mut allocator = CopyingHeap::<HEAP_SIZE, MAX_BLOCKS>::new();
-        let mut tracer = TestTracer::default();
-        let p = tracer.allocate_next(HEAP_SIZE, &mut allocator).unwrap();
-        tracer.deallocate_next().unwrap();
-        tracer.allocate_next(1, &mut allocator).unwrap();
-        let q = tracer.allocate_next(1, &mut allocator).unwrap();
-        assert_eq!(p.block_num(), q.block_num());
-        match allocator.load(p) {
-            Ok(_) => panic!("This should have been a MisalignedPointer error"),
-            Err(e) => assert_eq!(e, HeapError::MisalignedPointer(HEAP_SIZE, 1, p.block_num()))
+    fn growable_heap_test() {
+        let policy = GrowthPolicy { growth: Growth::Double, max_words: 16, max_blocks: 8 };
+        let mut allocator: VecCopyingHeap = VecCopyingHeap::new(8, 2, policy);
+        let mut roots = Roots::default();
+        for i in 0..3 {
+            let p = allocator.malloc(4, &roots).unwrap();
+            allocator.store(p, i).unwrap();
+            roots.0.push(p);
+        }
+        assert_eq!((allocator.heaps[0].size(), allocator.marks.len()), (16, 4));
+        for (i, p) in roots.0.iter().enumerate() {
+            assert_eq!(allocator.load(*p).unwrap(), i as u64);
+        }
+        let p = allocator.malloc(4, &roots).unwrap();
+        roots.0.push(p);
+        assert_eq!(allocator.malloc(1, &roots), Err(HeapError::OutOfMemory));
+
+        // With nothing allowed to stay in generation 0, the third promotion needs a larger generation 1.
+        let policy = GrowthPolicy { growth: Growth::Increment(4), max_words: 12, max_blocks: 4 };
+        let mut allocator: VecGenerationalHeap<0> = VecGenerationalHeap::new(4, 4, policy);
+        let mut roots = Roots::default();
+        for i in 0..3 {
+            let p = allocator.malloc(4, &roots).unwrap();
+            allocator.store(p, i).unwrap();
+            roots.0.push(p);
+        }
+        assert_eq!((allocator.gen_0[0].size(), allocator.gen_1[0].size()), (4, 8));
+        for (i, p) in roots.0.iter().enumerate() {
+            assert_eq!(allocator.load(*p).unwrap(), i as u64);
         }
+
+        // Generation 0 grows for a request larger than it, and the block table grows up to its cap.
+        let policy = GrowthPolicy { growth: Growth::Double, max_words: 16, max_blocks: 2 };
+        let mut allocator: VecGenerationalHeap<2> = VecGenerationalHeap::new(4, 1, policy);
+        let mut roots = Roots::default();
+        roots.0.push(allocator.malloc(8, &roots).unwrap());
+        roots.0.push(allocator.malloc(1, &roots).unwrap());
+        assert_eq!(allocator.malloc(1, &roots), Err(HeapError::OutOfBlocks));
+        assert_eq!((allocator.gen_0[0].size(), allocator.gen_1[0].size(), allocator.marks.len()), (16, 8, 2));
+        assert!(allocator.verify().is_empty());
+
+        // A heap already past the cap is never shrunk.
+        let policy = GrowthPolicy { growth: Growth::Double, max_words: 4, max_blocks: 4 };
+        assert_eq!(policy.grown(8, 6, policy.max_words), Some(8));
+        assert_eq!(policy.grown(8, 9, policy.max_words), None);
+        assert_eq!(policy.grown(3, 4, policy.max_words), Some(4));
     }
 
     fn test_initial_allocation<H: GarbageCollectingHeap>(